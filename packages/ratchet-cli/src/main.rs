@@ -1,6 +1,8 @@
-use clap::{Parser, Subcommand};
-use ratchet::{RATCHET_CONFIG, RATCHET_FILE};
-use std::time::Instant;
+use clap::{Args, Parser, Subcommand};
+use ratchet::{
+    CompareReport, OutputFormat, RATCHET_CONFIG, RATCHET_FILE, RatchetError, RuleFilter, RunInfo,
+};
+use std::{path::PathBuf, process, time::Instant};
 
 /// Ratchet is a tool to help you add new rules to your project over time
 #[derive(Parser)]
@@ -29,6 +31,10 @@ enum Commands {
         /// Path for location of ratchet file, defaults to ratchet.ron in the current directory
         #[clap(long, short, default_value = RATCHET_FILE)]
         file: String,
+        #[command(flatten)]
+        rules: RuleArgs,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Check that no rules have been violated
     Check {
@@ -38,6 +44,13 @@ enum Commands {
         /// Path for location of ratchet file, defaults to ratchet.ron in the current directory
         #[clap(long, short, default_value = RATCHET_FILE)]
         file: String,
+        #[command(flatten)]
+        rules: RuleArgs,
+        /// Also fail if the ratchet file is out of date, like when things improved but nobody turned it
+        #[clap(long)]
+        strict: bool,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Force the results to be updated, even if they got worse
     Force {
@@ -47,21 +60,191 @@ enum Commands {
         /// Path for location of ratchet file, defaults to ratchet.ron in the current directory
         #[clap(long, short, default_value = RATCHET_FILE)]
         file: String,
+        #[command(flatten)]
+        rules: RuleArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Write a static HTML report of everything in the ratchet file
+    Report {
+        /// Path to the config file to use, defaults to ratchet.toml in the current directory
+        #[clap(long, short, default_value = RATCHET_CONFIG)]
+        config: String,
+        /// Path for location of ratchet file, defaults to ratchet.ron in the current directory
+        #[clap(long, short, default_value = RATCHET_FILE)]
+        file: String,
+        /// Directory to write the site to, it's created if it doesn't exist
+        #[clap(long, value_name = "DIR")]
+        html: PathBuf,
+    },
+    /// Check that the config file is valid without running any rules
+    Validate {
+        /// Path to the config file to use, defaults to ratchet.toml in the current directory
+        #[clap(long, short, default_value = RATCHET_CONFIG)]
+        config: String,
+    },
+    /// Upgrade the ratchet file to the latest format
+    Migrate {
+        /// Path to the config file to use, defaults to ratchet.toml in the current directory
+        #[clap(long, short, default_value = RATCHET_CONFIG)]
+        config: String,
+        /// Path for location of ratchet file, defaults to ratchet.ron in the current directory
+        #[clap(long, short, default_value = RATCHET_FILE)]
+        file: String,
     },
 }
 
+/// Limits which rules run, every other rule's results are left as they were
+#[derive(Args)]
+struct RuleArgs {
+    /// Only run the rule with this name, can be passed more than once
+    #[clap(long = "rule", value_name = "NAME")]
+    only: Vec<String>,
+    /// Skip the rule with this name, can be passed more than once
+    #[clap(long = "skip-rule", value_name = "NAME")]
+    skip: Vec<String>,
+}
+
+impl From<&RuleArgs> for RuleFilter {
+    fn from(args: &RuleArgs) -> Self {
+        RuleFilter {
+            only: args.only.clone(),
+            skip: args.skip.clone(),
+        }
+    }
+}
+
+/// Where and how the results of a run are reported
+#[derive(Args)]
+struct OutputArgs {
+    /// How to print the results, machine readable formats go to stdout and everything else to stderr
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// Also write a JUnit XML report to this path
+    #[clap(long, value_name = "PATH")]
+    junit_report: Option<PathBuf>,
+}
+
+/// Exit code when checking finds that a rule got worse, or in strict mode that the ratchet file is stale
+const EXIT_GOT_WORSE: i32 = 1;
+/// Exit code when the arguments are invalid, the same code clap exits with for its own usage errors
+const EXIT_USAGE: i32 = 2;
+/// Exit code when a file couldn't be read or written, including a corrupt ratchet file
+const EXIT_IO_FAILURE: i32 = 3;
+/// Exit code when the config file or its rules are invalid
+const EXIT_CONFIG_INVALID: i32 = 4;
+/// Exit code when a rule failed to run, like an external tool that crashed or timed out
+const EXIT_RULE_FAILED: i32 = 5;
+
+impl Commands {
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Init { .. } => "init",
+            Commands::Turn { .. } => "turn",
+            Commands::Check { .. } => "check",
+            Commands::Force { .. } => "force",
+            Commands::Report { .. } => "report",
+            Commands::Validate { .. } => "validate",
+            Commands::Migrate { .. } => "migrate",
+        }
+    }
+
+    fn output(&self) -> Option<&OutputArgs> {
+        match self {
+            Commands::Turn { output, .. }
+            | Commands::Check { output, .. }
+            | Commands::Force { output, .. } => Some(output),
+            _ => None,
+        }
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
+    let output = cli.command.output();
+    let format = output.map_or(OutputFormat::Human, |output| output.format);
+    ratchet::human_output_to_stderr(format.is_machine_readable());
 
     let start = Instant::now();
 
-    match &cli.command {
-        Commands::Init { config } => ratchet::init(config),
-        Commands::Turn { config, file } => ratchet::turn(config, file),
-        Commands::Check { config, file } => ratchet::check(config, file),
-        Commands::Force { config, file } => ratchet::force(config, file),
+    // Each command gives back whether it failed, and the report if it has one
+    let result: ratchet::Result<(bool, Option<CompareReport>)> = match &cli.command {
+        Commands::Init { config } => ratchet::init(config).map(|_| (false, None)),
+        // Turning just refuses to save when things got worse, it doesn't fail the process
+        Commands::Turn {
+            config,
+            file,
+            rules,
+            ..
+        } => ratchet::turn(config, file, &rules.into()).map(|report| (false, Some(report))),
+        Commands::Check {
+            config,
+            file,
+            rules,
+            strict,
+            ..
+        } => ratchet::check(config, file, &rules.into(), *strict).map(|report| {
+            let failed = report.got_worse() || (*strict && report.is_stale());
+            (failed, Some(report))
+        }),
+        Commands::Force {
+            config,
+            file,
+            rules,
+            ..
+        } => ratchet::force(config, file, &rules.into()).map(|report| (false, Some(report))),
+        Commands::Report { config, file, html } => {
+            ratchet::report(config, file, html).map(|_| (false, None))
+        }
+        Commands::Validate { config } => ratchet::validate(config).map(|_| (false, None)),
+        Commands::Migrate { config, file } => ratchet::migrate(config, file).map(|_| (false, None)),
+    };
+
+    let duration = start.elapsed();
+    let finished = format!("\n⚡Ratchet finished in {:.2}s", duration.as_secs_f32());
+    if format.is_machine_readable() {
+        eprintln!("{finished}");
+    } else {
+        println!("{finished}");
     }
 
-    let duration = start.elapsed().as_secs_f32();
-    println!("\n⚡Ratchet finished in {duration:.2}s");
+    let run = RunInfo {
+        command: cli.command.name(),
+        duration,
+    };
+    let result = result.and_then(|(failed, report)| {
+        if let Some(report) = report {
+            if let Some(rendered) = format.render(&report, &run)? {
+                print!("{rendered}");
+                if !rendered.is_empty() && !rendered.ends_with('\n') {
+                    println!();
+                }
+            }
+            if let Some(path) = output.and_then(|output| output.junit_report.as_ref()) {
+                OutputFormat::Junit.write(&report, &run, path)?;
+            }
+        }
+        Ok(failed)
+    });
+
+    match result {
+        Ok(false) => {}
+        Ok(true) => process::exit(EXIT_GOT_WORSE),
+        Err(error) => {
+            eprintln!("❌ {error}");
+            process::exit(exit_code(&error));
+        }
+    }
+}
+
+fn exit_code(error: &RatchetError) -> i32 {
+    if error.is_config_error() {
+        EXIT_CONFIG_INVALID
+    } else if error.is_usage_error() {
+        EXIT_USAGE
+    } else if error.is_rule_failure() {
+        EXIT_RULE_FAILED
+    } else {
+        EXIT_IO_FAILURE
+    }
 }
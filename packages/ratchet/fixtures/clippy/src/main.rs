@@ -0,0 +1,11 @@
+#![warn(clippy::unwrap_used)]
+
+fn double(x: i32) -> i32 {
+    return x * 2;
+}
+
+fn main() {
+    let unused = 5;
+    let value: Option<i32> = Some(1);
+    println!("{}", double(value.unwrap()));
+}
@@ -0,0 +1,6 @@
+const unused = 1;
+
+export function greet(name: any) {
+  console.log("hello " + name);
+  return eval(name);
+}
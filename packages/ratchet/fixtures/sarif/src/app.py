@@ -0,0 +1,9 @@
+import subprocess
+
+
+def run(command):
+    return subprocess.call(command, shell=True)
+
+
+def load(data):
+    return eval(data)
@@ -1,55 +1,104 @@
-use std::{collections::BTreeMap, fs, io::Write};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{ratchet_file::RATCHET_FILE, rules::RatchetRule};
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::RATCHET_FILE,
+    rules::RatchetRule,
+};
 
 pub const CONFIG_VERSION: u8 = 1;
 pub const RATCHET_CONFIG: &str = "ratchet.toml";
 
+/// Name of the ratchet specific ignore file, it works just like a .gitignore
+pub const RATCHET_IGNORE: &str = ".ratchetignore";
+
 // TODO: What else should be considered well known?
-pub const WELL_KNOWN_FILES: [&str; 4] = [
-    // Ratchet specific
-    RATCHET_FILE,
-    RATCHET_CONFIG,
+/// Files ratchet manages itself, skipped wherever they are
+pub const WELL_KNOWN_FILES: [&str; 2] = [RATCHET_FILE, RATCHET_CONFIG];
+/// Directories that are never worth walking into, skipped wherever they are
+pub const WELL_KNOWN_DIRS: [&str; 1] = [
     // No need to look in the git folder!
     ".git",
+];
+/// Directories only skipped at the root of the scan, in case ignore files are turned off
+pub const WELL_KNOWN_ROOT_DIRS: [&str; 1] = [
     // Rust specific
-    // TODO: What if they're not running from root directory
-    "./target",
+    "target",
 ];
 
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct RatchetConfig {
     pub version: u8,
+    /// Directory to scan, relative to the config file. Defaults to the directory the config is in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    /// Skip files matched by .gitignore, .ignore, and .ratchetignore files
+    #[serde(default = "default_use_ignore_files")]
+    pub use_ignore_files: bool,
     // TODO: I don't think I like this structure, revisit
     pub rules: BTreeMap<String, RatchetRule>,
 }
 
+fn default_use_ignore_files() -> bool {
+    true
+}
+
 impl RatchetConfig {
     pub fn new() -> Self {
         RatchetConfig {
             version: CONFIG_VERSION,
+            root: None,
+            use_ignore_files: default_use_ignore_files(),
             rules: BTreeMap::new(),
         }
     }
 
-    pub fn init() {
+    pub fn init(config_path: &str) -> Result<()> {
         let ratchet_config = RatchetConfig::new();
 
-        let toml = toml::to_string(&ratchet_config).expect("Failed to serialize");
+        let toml = toml::to_string(&ratchet_config).map_err(RatchetError::SerializeConfig)?;
         let toml = format!("{toml}\n");
 
-        let mut file = fs::File::create(RATCHET_CONFIG).expect("Failed to create file");
-        file.write_all(toml.as_bytes())
-            .expect("Failed to write to file");
+        fs::write(config_path, toml).map_err(|e| RatchetError::io(config_path, e))
+    }
+
+    /// Resolves the directory to scan, every path in the ratchet file is relative to it
+    pub fn root_dir(&self, config_path: &str) -> PathBuf {
+        let config_dir = match Path::new(config_path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        match &self.root {
+            Some(root) => config_dir.join(root),
+            None => config_dir.to_path_buf(),
+        }
     }
 }
 
-pub fn read_config(config_path: &String) -> RatchetConfig {
-    let contents = fs::read_to_string(config_path).expect("Something went wrong reading the file");
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
 
-    let ratchet_config: RatchetConfig = toml::from_str(&contents).expect("Failed to deserialize");
+    use super::RatchetConfig;
 
-    ratchet_config
+    #[test]
+    fn root_is_relative_to_the_config_file() {
+        let mut config = RatchetConfig::new();
+        assert_eq!(config.root_dir("ratchet.toml"), Path::new("."));
+        assert_eq!(config.root_dir("../ratchet.toml"), Path::new(".."));
+
+        config.root = Some(PathBuf::from("src"));
+        assert_eq!(
+            config.root_dir("project/ratchet.toml"),
+            Path::new("project/src")
+        );
+    }
 }
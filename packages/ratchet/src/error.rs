@@ -0,0 +1,140 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::{ratchet_file::RATCHET_FILE_VERSION, validate::ConfigDiagnostic};
+
+pub type Result<T> = std::result::Result<T, RatchetError>;
+
+/// Everything that can go wrong while running ratchet
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum RatchetError {
+    /// Reading or writing a file failed
+    Io { path: PathBuf, source: io::Error },
+    /// Walking the directory tree failed
+    Walk(ignore::Error),
+    /// The config file isn't valid TOML, doesn't match the expected shape, or has rules that don't compile
+    InvalidConfig {
+        path: PathBuf,
+        diagnostics: Vec<ConfigDiagnostic>,
+    },
+    /// A rule couldn't check the project, like when the command it runs fails or times out
+    RuleFailed { rule: String, message: String },
+    /// A rule was asked for by name but isn't in the config
+    UnknownRule(String),
+    /// The ratchet file couldn't be parsed, it may be corrupt or an old version
+    InvalidRatchetFile {
+        path: PathBuf,
+        source: Box<ron::error::SpannedError>,
+    },
+    /// The ratchet file was written by a different version of ratchet
+    UnsupportedRatchetFileVersion { path: PathBuf, version: u8 },
+    /// The config couldn't be turned into TOML
+    SerializeConfig(toml::ser::Error),
+    /// The ratchet file couldn't be turned into RON
+    SerializeRatchetFile(ron::Error),
+    /// A machine readable report couldn't be rendered
+    SerializeReport(serde_json::Error),
+}
+
+impl RatchetError {
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        RatchetError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// True if the error was caused by a bad config rather than the file system
+    pub fn is_config_error(&self) -> bool {
+        matches!(self, RatchetError::InvalidConfig { .. })
+    }
+
+    /// True if a rule couldn't produce results, like a linter it runs crashing or timing out
+    pub fn is_rule_failure(&self) -> bool {
+        matches!(self, RatchetError::RuleFailed { .. })
+    }
+
+    /// True if the error was caused by how ratchet was called, like asking for a rule that doesn't exist
+    pub fn is_usage_error(&self) -> bool {
+        matches!(self, RatchetError::UnknownRule(_))
+    }
+}
+
+impl fmt::Display for RatchetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RatchetError::Io { path, source } => {
+                write!(f, "Failed to access {}: {}", path.display(), source)
+            }
+            RatchetError::Walk(source) => write!(f, "Failed to walk the directory tree: {source}"),
+            RatchetError::InvalidConfig { path, diagnostics } => {
+                write!(f, "Invalid config file {}:", path.display())?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n    {diagnostic}")?;
+                }
+                Ok(())
+            }
+            RatchetError::RuleFailed { rule, message } => {
+                write!(f, "Rule \"{rule}\" failed: {message}")
+            }
+            RatchetError::UnknownRule(name) => write!(f, "Rule \"{name}\" is not in the config"),
+            RatchetError::InvalidRatchetFile { path, source } => write!(
+                f,
+                "Failed to read ratchet file {}, it may be corrupt or an old version: {}",
+                path.display(),
+                source
+            ),
+            RatchetError::UnsupportedRatchetFileVersion { path, version } => {
+                if *version < RATCHET_FILE_VERSION {
+                    write!(
+                        f,
+                        "Ratchet file {} is version {}, run `ratchet migrate` to upgrade it to version {}",
+                        path.display(),
+                        version,
+                        RATCHET_FILE_VERSION
+                    )
+                } else {
+                    write!(
+                        f,
+                        "Ratchet file {} is version {}, which is newer than this ratchet supports ({})",
+                        path.display(),
+                        version,
+                        RATCHET_FILE_VERSION
+                    )
+                }
+            }
+            RatchetError::SerializeConfig(source) => {
+                write!(f, "Failed to serialize config: {source}")
+            }
+            RatchetError::SerializeRatchetFile(source) => {
+                write!(f, "Failed to serialize ratchet file: {source}")
+            }
+            RatchetError::SerializeReport(source) => {
+                write!(f, "Failed to serialize report: {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RatchetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RatchetError::Io { source, .. } => Some(source),
+            RatchetError::Walk(source) => Some(source),
+            RatchetError::InvalidConfig { .. } => None,
+            RatchetError::RuleFailed { .. } => None,
+            RatchetError::UnknownRule(_) => None,
+            RatchetError::InvalidRatchetFile { source, .. } => Some(source.as_ref()),
+            RatchetError::UnsupportedRatchetFileVersion { .. } => None,
+            RatchetError::SerializeConfig(source) => Some(source),
+            RatchetError::SerializeRatchetFile(source) => Some(source),
+            RatchetError::SerializeReport(source) => Some(source),
+        }
+    }
+}
+
+impl From<ignore::Error> for RatchetError {
+    fn from(error: ignore::Error) -> Self {
+        RatchetError::Walk(error)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::RuleName,
+};
+
+/// Limits a run to some of the configured rules, every other rule's results are left as they were
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RuleFilter {
+    /// Only run these rules, or every rule if empty
+    pub only: Vec<RuleName>,
+    /// Never run these rules, even if they're in `only`
+    pub skip: Vec<RuleName>,
+}
+
+impl RuleFilter {
+    /// True if every rule should run
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty()
+    }
+
+    pub fn includes(&self, rule: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|name| name == rule))
+            && !self.skip.iter().any(|name| name == rule)
+    }
+
+    /// Makes sure every rule named in the filter exists, so a typo doesn't silently run nothing
+    pub fn validate<T>(&self, rules: &BTreeMap<RuleName, T>) -> Result<()> {
+        match self
+            .only
+            .iter()
+            .chain(&self.skip)
+            .find(|name| !rules.contains_key(*name))
+        {
+            Some(name) => Err(RatchetError::UnknownRule(name.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::RuleFilter;
+
+    #[test]
+    fn skip_wins_over_only() {
+        let filter = RuleFilter {
+            only: vec!["a".into(), "b".into()],
+            skip: vec!["b".into()],
+        };
+
+        assert!(filter.includes("a"));
+        assert!(!filter.includes("b"));
+        assert!(!filter.includes("c"));
+        assert!(RuleFilter::default().includes("c"));
+    }
+
+    #[test]
+    fn unknown_rules_are_rejected() {
+        let mut rules = BTreeMap::new();
+        rules.insert("a".to_string(), ());
+
+        let filter = RuleFilter {
+            only: vec!["a".into()],
+            skip: vec!["typo".into()],
+        };
+
+        assert!(matches!(
+            filter.validate(&rules),
+            Err(crate::RatchetError::UnknownRule(name)) if name == "typo"
+        ));
+    }
+}
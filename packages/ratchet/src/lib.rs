@@ -1,9 +1,29 @@
 mod config;
+mod error;
+mod filter;
+mod migrate;
+mod output;
 mod ratchet;
 mod ratchet_file;
+mod report;
+pub mod reporters;
 mod rules;
 mod utils;
+mod validate;
+mod walk;
 
 pub use crate::config::RATCHET_CONFIG;
-pub use crate::ratchet::{check, force, init, turn};
-pub use crate::ratchet_file::RATCHET_FILE;
+pub use crate::error::{RatchetError, Result};
+pub use crate::filter::RuleFilter;
+pub use crate::output::human_output_to_stderr;
+pub use crate::ratchet::{check, force, init, migrate, report, turn, validate};
+pub use crate::ratchet_file::{
+    FileEntry, FileName, Fingerprint, Position, Problem, RATCHET_FILE, RATCHET_FILE_VERSION,
+    RatchetFile, RuleMap, RuleName,
+};
+pub use crate::report::{CompareReport, FileDelta, RuleReport, RuleStatus};
+pub use crate::reporters::{OutputFormat, RunInfo};
+pub use crate::rules::path_matcher::{PatternError, PatternSyntax};
+pub use crate::rules::rule::Severity;
+pub use crate::rules::rule_set::{PatternKind, RuleCompileError};
+pub use crate::validate::ConfigDiagnostic;
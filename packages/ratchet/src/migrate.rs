@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{RatchetError, Result},
+    output::say,
+    ratchet_file::{FileEntry, Problem, RATCHET_FILE_VERSION, RatchetFile, RuleName, Versioned},
+    utils::{LineIndex, fingerprint, to_normalized_file_contents},
+};
+
+/// The original ratchet file format, where files and problems were anonymous tuples
+/// and problems were tracked by byte offsets
+#[derive(Deserialize)]
+struct RatchetFileV1 {
+    rules: BTreeMap<RuleName, RuleMapV1>,
+}
+
+type RuleMapV1 = BTreeMap<(String, u64), Vec<ProblemV1>>;
+/// Start and end byte offsets, message text, and message hash
+type ProblemV1 = (usize, usize, String, String);
+
+/// Upgrades the ratchet file to the current version, returning false if it was already up to date.
+/// Files it tracks are read relative to `root`, the directory ratchet scans
+pub fn migrate_file(file: &str, root: &Path) -> Result<bool> {
+    let contents = read_to_string(file).map_err(|e| RatchetError::io(file, e))?;
+    let invalid = |source| RatchetError::InvalidRatchetFile {
+        path: file.into(),
+        source: Box::new(source),
+    };
+
+    let Versioned { version } = ron::de::from_str(&contents).map_err(invalid)?;
+    let migrated = match version {
+        RATCHET_FILE_VERSION => return Ok(false),
+        1 => from_v1(ron::de::from_str(&contents).map_err(invalid)?, root),
+        _ => {
+            return Err(RatchetError::UnsupportedRatchetFileVersion {
+                path: file.into(),
+                version,
+            });
+        }
+    };
+
+    migrated.save(file)?;
+    Ok(true)
+}
+
+/// Byte offsets are converted to positions and fingerprints using the files as they are now.
+/// If a file changed since it was last ratcheted, its positions are a best guess until the next turn.
+fn from_v1(v1: RatchetFileV1, root: &Path) -> RatchetFile {
+    let mut ratchet_file = RatchetFile::new();
+
+    for (rule, files) in v1.rules {
+        let mut rule_map = Vec::new();
+
+        for ((path, hash), problems) in files {
+            let Ok(content) = read_to_string(root.join(&path)) else {
+                say!("⚠️ {path} no longer exists, dropping its problems for {rule}");
+                continue;
+            };
+            let content = to_normalized_file_contents(&content);
+
+            if seahash::hash(content.as_bytes()) != hash {
+                say!("⚠️ {path} changed since it was last ratcheted, positions may be off");
+            }
+
+            let line_index = LineIndex::new(&content);
+            let problems: Vec<Problem> = problems
+                .into_iter()
+                .filter(|(start, end, _, _)| {
+                    let valid = start <= end && content.get(*start..*end).is_some();
+                    if !valid {
+                        say!(
+                            "⚠️ {path} is too different to place a problem for {rule}, dropping it"
+                        );
+                    }
+                    valid
+                })
+                .map(|(start, end, message, message_hash)| Problem {
+                    start: line_index.position(start),
+                    end: line_index.position(end),
+                    message,
+                    message_hash,
+                    fingerprint: fingerprint(&content, start, end),
+                })
+                .collect();
+
+            // Version 1 paths started with ./ but paths are now relative to the root
+            let path = path.strip_prefix("./").map(str::to_string).unwrap_or(path);
+            rule_map.push(FileEntry {
+                path,
+                hash,
+                problems,
+            });
+        }
+
+        ratchet_file.rules.insert(rule, rule_map);
+    }
+
+    ratchet_file
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use super::{RatchetFileV1, from_v1};
+
+    #[test]
+    fn v1_files_are_converted_to_named_problems() {
+        // Version 1 paths are read from the root, not wherever ratchet was run from
+        let root = env::temp_dir().join(format!("ratchet-migrate-v1-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        let content = "fn main() {}\n// TODO: migrate\n";
+        fs::write(root.join("src/main.rs"), content).unwrap();
+
+        let start = content.find("TODO").unwrap();
+        let v1 = format!(
+            r#"(version: 1, rules: {{ "No TODOs": {{ ("./src/main.rs", {hash}): [({start}, {end}, "TODO", "123")] }} }})"#,
+            hash = seahash::hash(content.as_bytes()),
+            end = start + 4,
+        );
+        let v1: RatchetFileV1 = ron::de::from_str(&v1).unwrap();
+
+        let migrated = from_v1(v1, &root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let entry = &migrated.rules["No TODOs"][0];
+        assert_eq!(entry.path, "src/main.rs");
+        assert_eq!(entry.problems.len(), 1);
+        assert_eq!(entry.problems[0].start.to_string(), "2:4");
+        assert_eq!(entry.problems[0].end.to_string(), "2:8");
+        assert_eq!(entry.problems[0].message, "TODO");
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Sends human readable output to stderr so stdout only carries a machine readable report
+pub fn human_output_to_stderr(to_stderr: bool) {
+    TO_STDERR.store(to_stderr, Ordering::Relaxed);
+}
+
+/// Prints a line of human readable output to stdout, or stderr if it was redirected
+pub fn print_human(args: fmt::Arguments) {
+    if TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{args}");
+    } else {
+        println!("{args}");
+    }
+}
+
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::print_human(format_args!($($arg)*))
+    };
+}
+
+pub(crate) use say;
@@ -1,110 +1,508 @@
-use std::{collections::BTreeMap, fs::read_to_string, path::Path, process};
-use walkdir::WalkDir;
+use rayon::prelude::*;
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
 
 use crate::{
-    config::{self, WELL_KNOWN_FILES, read_config},
-    ratchet_file::{RatchetFile, RuleMap, RuleName},
-    rules::rule::Rule,
+    config,
+    error::Result,
+    filter::RuleFilter,
+    migrate::migrate_file,
+    output::say,
+    ratchet_file::{FileEntry, Problem, RATCHET_FILE_VERSION, RatchetFile, RuleMap, RuleName},
+    report::{CompareReport, RuleStatus},
+    reporters::html,
+    rules::{
+        rule::Severity,
+        rule_set::{CompiledRule, RuleSet},
+    },
     utils::{to_normalized_file_contents, to_normalized_path},
+    validate::load_config,
+    walk::collect_files,
 };
 
-pub fn init(config: &String) {
-    println!("🎬 Initializing ratchet!\n");
+pub fn init(config: &str) -> Result<()> {
+    say!("🎬 Initializing ratchet!\n");
 
     let path = Path::new(config);
     if path.exists() {
-        println!("Ratchet config already exists at {config}");
-        return;
+        say!("Ratchet config already exists at {config}");
+        return Ok(());
     }
 
-    config::RatchetConfig::init();
+    config::RatchetConfig::init(config)
 }
 
-pub fn turn(config: &String, file: &String) {
-    println!("⚙️ Turning ratchet!\n");
-    let (got_worse, new_ratchet) = process_rules(config, file);
+pub fn turn(config: &str, file: &str, filter: &RuleFilter) -> Result<CompareReport> {
+    say!("⚙️ Turning ratchet!\n");
+    let (report, new_ratchet) = process_rules(config, file, filter)?;
+    print_report(&report);
 
-    if !got_worse {
-        new_ratchet.save(file);
+    if !report.got_worse() {
+        new_ratchet.save(file)?;
     }
+
+    Ok(report)
 }
 
-pub fn check(config: &String, file: &String) {
-    println!("👀 Checking ratchet!\n");
-    let (got_worse, _) = process_rules(config, file);
+/// In strict mode, anything that would change the ratchet file is also printed,
+/// use `CompareReport::is_stale` to fail on it
+pub fn check(config: &str, file: &str, filter: &RuleFilter, strict: bool) -> Result<CompareReport> {
+    say!("👀 Checking ratchet!\n");
+    let (report, _) = process_rules(config, file, filter)?;
+    print_report(&report);
 
-    if got_worse {
-        process::exit(1);
+    if strict {
+        print_stale(&report, file);
     }
+
+    Ok(report)
 }
 
-pub fn force(config: &String, file: &String) {
-    println!("⛓️‍💥 Forcing ratchet!\n");
-    let (_, new_ratchet) = process_rules(config, file);
+pub fn force(config: &str, file: &str, filter: &RuleFilter) -> Result<CompareReport> {
+    say!("⛓️‍💥 Forcing ratchet!\n");
+    let (report, new_ratchet) = process_rules(config, file, filter)?;
+    print_report(&report);
 
     // We don't care if things got better or worse, we're saving regardless!
-    new_ratchet.save(file);
+    new_ratchet.save(file)?;
+
+    Ok(report)
 }
 
-fn process_rules(config_path: &String, file: &String) -> (bool, RatchetFile) {
-    let config = read_config(config_path);
-    // HACK: Test comment to get it in the RATCHET_FILE file
+/// Writes a static HTML site of everything in the ratchet file, without running any rules
+pub fn report(config: &str, file: &str, html_dir: &Path) -> Result<()> {
+    say!("📊 Reporting ratchet!\n");
 
-    let previous_ratchet = RatchetFile::load(file);
+    let (config_file, _) = load_config(config)?;
+    let root = config_file.root_dir(config);
+    let ratchet_file = RatchetFile::load(file)?;
 
-    let mut rules_map: BTreeMap<RuleName, RuleMap> = BTreeMap::new();
+    html::write_site(html_dir, &ratchet_file, &config_file, config, &root)?;
+    say!(
+        "Report written to {}",
+        html_dir.join("index.html").display()
+    );
 
-    // TODO: Parallelize this someday
-    config.rules.iter().for_each(|(key, rule)| {
-        let mut rule_map: RuleMap = BTreeMap::new();
+    Ok(())
+}
 
-        for entry in WalkDir::new(".") {
-            let entry = entry.unwrap();
-            // If it's not a file, there's nothing to analyze. Keep going!
-            if !entry.file_type().is_file() {
-                continue;
-            }
+pub fn validate(config: &str) -> Result<()> {
+    say!("🔍 Validating ratchet config!\n");
 
-            let os_path = entry.path();
-            let path_str = to_normalized_path(os_path);
-            if WELL_KNOWN_FILES
-                .iter()
-                .any(|&pattern| path_str.ends_with(pattern) || path_str.contains(pattern))
-            {
-                continue;
-            }
+    let (config_file, _) = load_config(config)?;
+    say!("✅ {} is valid ({} rules)", config, config_file.rules.len());
 
-            if !rule.analyze_file(&path_str) {
-                println!("Skipping: {} for {}", os_path.display(), key);
-                continue;
-            }
+    Ok(())
+}
+
+/// Upgrades the ratchet file, reading the files it tracks from the config's root
+pub fn migrate(config: &str, file: &str) -> Result<()> {
+    say!("🚚 Migrating ratchet file!\n");
+
+    let (config_file, _) = load_config(config)?;
+    if migrate_file(file, &config_file.root_dir(config))? {
+        say!("Ratchet file {file} upgraded to version {RATCHET_FILE_VERSION}");
+    } else {
+        say!("Ratchet file {file} is already version {RATCHET_FILE_VERSION}");
+    }
 
-            let content = read_to_string(os_path);
-            if let Err(_e) = content {
-                // println!("Failed to read file, continuing: {:?}", e);
-                continue;
+    Ok(())
+}
+
+fn print_report(report: &CompareReport) {
+    for (rule, rule_report) in &report.rules {
+        let previous_count = rule_report.previous_count;
+        let new_count = rule_report.new_count;
+
+        if rule_report.severity == Severity::Off {
+            say!("💤 Rule {rule} is off ({previous_count} issues kept as they were)");
+            continue;
+        }
+
+        match rule_report.status {
+            RuleStatus::Added => {
+                say!("🆕 Rule {rule} was added ({new_count} total)");
+            }
+            RuleStatus::Removed => {
+                say!("🗑️ Rule {rule} was removed ({previous_count} issues no longer tracked)");
+            }
+            RuleStatus::Worsened => {
+                let icon = match rule_report.severity {
+                    Severity::Warn => "⚠️",
+                    _ => "❌",
+                };
+                say!(
+                    "{} Rule {} got worse ({} new issues out of {} total)",
+                    icon,
+                    rule,
+                    new_count - previous_count,
+                    new_count
+                );
+                for (file_name, problem) in &rule_report.new_problems {
+                    say!("    {}:{} ({})", file_name, problem.start, problem.message);
+                }
             }
+            RuleStatus::Improved => {
+                say!(
+                    "🛠️ Rule {} improved ({} issues fixed out of {} total)",
+                    rule,
+                    previous_count - new_count,
+                    new_count
+                );
+                for (file_name, problem) in &rule_report.fixed_problems {
+                    say!("    {}:{} ({})", file_name, problem.start, problem.message);
+                }
+            }
+            RuleStatus::Unchanged => {
+                say!("✔️ Rule {rule} did not change ({new_count} total)");
+            }
+        }
+    }
+}
 
-            let content = content.unwrap();
-            let content = to_normalized_file_contents(&content);
+fn print_stale(report: &CompareReport, file: &str) {
+    if !report.is_stale() {
+        return;
+    }
 
-            let problems = rule.check(&path_str, &content);
-            if problems.is_empty() {
-                continue;
+    say!("\n🔄 {file} is out of date, run `ratchet turn` to update it");
+    for (rule, rule_report) in report.stale() {
+        match rule_report.status {
+            RuleStatus::Added => say!("    Rule {rule} needs to be added"),
+            RuleStatus::Removed => say!("    Rule {rule} needs to be removed"),
+            _ => {
+                for file_name in &rule_report.stale_files {
+                    say!("    Rule {rule} needs updating for {file_name}");
+                }
             }
+        }
+    }
+}
+
+fn process_rules(
+    config_path: &str,
+    file: &str,
+    filter: &RuleFilter,
+) -> Result<(CompareReport, RatchetFile)> {
+    // Validate and compile every rule before scanning so bad patterns are caught before any work is done
+    let (config, mut rule_set) = load_config(config_path)?;
+    // HACK: Test comment to get it in the RATCHET_FILE file
+    filter.validate(&config.rules)?;
+
+    // Rules that are off or filtered out aren't run at all
+    rule_set
+        .rules
+        .retain(|rule| rule.severity != Severity::Off && filter.includes(&rule.name));
+    let severities: BTreeMap<RuleName, Severity> = config
+        .rules
+        .iter()
+        .map(|(key, rule)| (key.to_string(), rule.severity()))
+        .collect();
+
+    let previous_ratchet = RatchetFile::load(file)?;
+
+    // Walk the tree a single time, every rule works off the same list of files
+    let root = config.root_dir(config_path);
+    let paths = collect_files(
+        &root,
+        config.use_ignore_files,
+        &[Path::new(config_path), Path::new(file)],
+    )?;
+
+    // Rules that look at the whole project, like ones running another tool, do it before any file is read
+    for rule in &rule_set.rules {
+        rule.prepare(&root)?;
+    }
+
+    // Each file is read once and run through every rule that applies to it
+    let scanned: Vec<ScannedFile> = paths
+        .par_iter()
+        .filter_map(|os_path| scan_file(&rule_set, &root, os_path).transpose())
+        .collect::<Result<_>>()?;
+
+    // Every rule that ran gets an entry, even if it found nothing.
+    // Rules that didn't run keep their previous results exactly as they were,
+    // so turning them back on doesn't lose the baseline. If they have no results yet
+    // they get none, otherwise their first real run would count every problem as new
+    let mut rules_map: BTreeMap<RuleName, RuleMap> = severities
+        .iter()
+        .filter_map(|(key, severity)| {
+            let rule_map = if *severity == Severity::Off || !filter.includes(key) {
+                previous_ratchet.rules.get(key)?.clone()
+            } else {
+                Vec::new()
+            };
+            Some((key.to_string(), rule_map))
+        })
+        .collect();
 
-            let file_hash = seahash::hash(content.as_bytes());
-            rule_map.insert((path_str, file_hash), problems);
+    // When filtering, rules that were removed from the config aren't ours to drop either
+    if !filter.is_empty() {
+        for (key, rule_map) in &previous_ratchet.rules {
+            rules_map
+                .entry(key.to_string())
+                .or_insert_with(|| rule_map.clone());
         }
-        rules_map.insert(key.to_string(), rule_map);
-    });
+    }
+
+    for scanned_file in scanned {
+        for (key, problems) in scanned_file.problems {
+            if let Some(rule_map) = rules_map.get_mut(key) {
+                rule_map.push(FileEntry {
+                    path: scanned_file.path.clone(),
+                    hash: scanned_file.hash,
+                    problems,
+                });
+            }
+        }
+    }
+
+    // Sort by path so the output doesn't depend on the order the file system walked things
+    for rule_map in rules_map.values_mut() {
+        rule_map.sort_by(|a, b| a.path.cmp(&b.path));
+    }
 
     let new_ratchet = RatchetFile {
-        version: config.version,
+        version: RATCHET_FILE_VERSION,
         rules: rules_map,
     };
 
-    let got_worse = previous_ratchet.compare(&new_ratchet);
+    let mut report = previous_ratchet
+        .compare(&new_ratchet)
+        .with_severities(&severities);
+    // Only report on the rules that were asked for
+    report.rules.retain(|key, _| filter.includes(key));
+
+    Ok((report, new_ratchet))
+}
+
+/// Results of running every applicable rule against a single file
+struct ScannedFile<'a> {
+    path: String,
+    hash: u64,
+    problems: Vec<(&'a RuleName, Vec<Problem>)>,
+}
+
+/// Reads a file once and checks it against every rule that wants to analyze it
+fn scan_file<'a>(
+    rule_set: &'a RuleSet,
+    root: &Path,
+    os_path: &Path,
+) -> Result<Option<ScannedFile<'a>>> {
+    // Paths are relative to the root, so the results are the same wherever ratchet is run from
+    let path_str = to_normalized_path(os_path.strip_prefix(root).unwrap_or(os_path));
+
+    let applicable_rules: Vec<&CompiledRule> = rule_set
+        .rules
+        .iter()
+        .filter(|rule| rule.analyze_file(&path_str))
+        .collect();
+
+    // No rule cares about this file, so don't bother reading it
+    if applicable_rules.is_empty() {
+        return Ok(None);
+    }
+
+    // Binary files aren't valid UTF-8, and there's nothing for a text rule to find in them
+    let Ok(content) = read_to_string(os_path) else {
+        return Ok(None);
+    };
+    let content = to_normalized_file_contents(&content);
+
+    let mut problems: Vec<(&RuleName, Vec<Problem>)> = Vec::new();
+    for rule in applicable_rules {
+        let found = rule.check(&path_str, &content)?;
+        if !found.is_empty() {
+            problems.push((&rule.name, found));
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ScannedFile {
+        path: path_str,
+        hash: seahash::hash(content.as_bytes()),
+        problems,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf};
+
+    use super::process_rules;
+    use crate::{
+        filter::RuleFilter,
+        ratchet_file::{Position, RatchetFile},
+        report::RuleStatus,
+    };
+
+    /// A project starting with a single file that has one problem for each of its rules
+    struct Project {
+        root: PathBuf,
+    }
+
+    impl Project {
+        fn new(name: &str) -> Self {
+            let root = env::temp_dir().join(format!("ratchet-{name}-{}", std::process::id()));
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("main.py"), "widget = 1\ngadget = 2\n").unwrap();
+            Project { root }
+        }
+
+        fn configure(&self, widgets_severity: &str) {
+            let config = format!(
+                r#"version = 1
+
+[rules.widgets]
+type = "Regex"
+regex = "widget"
+severity = "{widgets_severity}"
+
+[rules.gadgets]
+type = "Regex"
+regex = "gadget"
+"#
+            );
+            fs::write(self.root.join("ratchet.toml"), config).unwrap();
+        }
+
+        /// Replaces a file in the project, creating its directory if needed
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn ratchet_file(&self) -> RatchetFile {
+            RatchetFile::load(&self.path("ratchet.ron")).unwrap()
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.root.join(name).to_string_lossy().into_owned()
+        }
+
+        /// Runs the rules and saves the results, like `ratchet turn` does when nothing got worse
+        fn turn(&self, filter: &RuleFilter) -> crate::CompareReport {
+            let file = self.path("ratchet.ron");
+            let (report, new_ratchet) =
+                process_rules(&self.path("ratchet.toml"), &file, filter).unwrap();
+            if !report.got_worse() {
+                new_ratchet.save(&file).unwrap();
+            }
+            report
+        }
+    }
+
+    #[test]
+    fn rules_turned_on_start_a_new_baseline() {
+        let project = Project::new("off-to-error");
+        project.configure("off");
+        let report = project.turn(&RuleFilter::default());
+        assert!(!report.rules.contains_key("widgets"));
+
+        // The rule never ran, so its existing problems are its baseline instead of new problems
+        project.configure("error");
+        let report = project.turn(&RuleFilter::default());
+        assert_eq!(report.rules["widgets"].status, RuleStatus::Added);
+        assert!(!report.got_worse());
+    }
+
+    #[test]
+    fn filtered_rules_without_results_stay_out_of_the_ratchet_file() {
+        let project = Project::new("filtered");
+        project.configure("error");
+        let only_gadgets = RuleFilter {
+            only: vec!["gadgets".into()],
+            skip: Vec::new(),
+        };
+        let report = project.turn(&only_gadgets);
+        assert!(!report.rules.contains_key("widgets"));
+
+        let report = project.turn(&RuleFilter::default());
+        assert_eq!(report.rules["widgets"].status, RuleStatus::Added);
+        assert_eq!(report.rules["gadgets"].status, RuleStatus::Unchanged);
+        assert!(!report.got_worse());
+    }
+
+    #[test]
+    fn turning_twice_writes_the_same_sorted_file() {
+        let project = Project::new("deterministic");
+        project.configure("error");
+        for path in [
+            "zeta.py",
+            "alpha/widget.py",
+            "beta.py",
+            "alpha/beta/deep.py",
+        ] {
+            project.write(path, "widget = widget\n");
+        }
+
+        // Files are scanned in parallel, so nothing about the order they finish in can leak into the file
+        project.turn(&RuleFilter::default());
+        let first = fs::read_to_string(project.path("ratchet.ron")).unwrap();
+        fs::remove_file(project.path("ratchet.ron")).unwrap();
+        project.turn(&RuleFilter::default());
+        let second = fs::read_to_string(project.path("ratchet.ron")).unwrap();
+        assert_eq!(first, second);
+
+        let ratchet_file = project.ratchet_file();
+        let paths: Vec<&str> = ratchet_file.rules["widgets"]
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "alpha/beta/deep.py",
+                "alpha/widget.py",
+                "beta.py",
+                "main.py",
+                "zeta.py"
+            ]
+        );
+    }
 
-    (got_worse, new_ratchet)
+    #[test]
+    fn problems_moved_by_an_edit_above_them_are_unchanged() {
+        let project = Project::new("moved");
+        project.configure("error");
+        project.turn(&RuleFilter::default());
+
+        project.write("main.py", "import os\n\nwidget = 1\ngadget = 2\n");
+        let report = project.turn(&RuleFilter::default());
+        assert_eq!(report.rules["widgets"].status, RuleStatus::Unchanged);
+        assert!(report.rules["widgets"].new_problems.is_empty());
+
+        // The saved position follows the problem to its new line
+        let ratchet_file = project.ratchet_file();
+        let moved = &ratchet_file.rules["widgets"][0].problems[0];
+        assert_eq!(moved.start, Position::new(3, 1));
+    }
+
+    #[test]
+    fn positions_count_characters_on_crlf_lines() {
+        let project = Project::new("crlf");
+        project.configure("error");
+        project.turn(&RuleFilter::default());
+
+        project.write(
+            "main.py",
+            "gadget = 'Zoë'\r\nlabel = 'ñandú'; widget = 1\r\n",
+        );
+        let report = project.turn(&RuleFilter::default());
+        let locations: Vec<String> = report.rules["widgets"]
+            .new_problems
+            .iter()
+            .map(|(path, problem)| format!("{path}:{}-{}", problem.start, problem.end))
+            .collect();
+        assert_eq!(locations, vec!["main.py:2:18-2:24"]);
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
 }
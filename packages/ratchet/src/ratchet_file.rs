@@ -1,14 +1,19 @@
+use crate::{
+    error::{RatchetError, Result},
+    output::say,
+    report::CompareReport,
+};
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 use std::{
-    cmp::Ordering,
     collections::BTreeMap,
-    fs::{File, read_to_string},
-    io::Write,
+    fmt,
+    fs::{self, read_to_string},
+    io::ErrorKind,
 };
 
-/// Current version of the ratchet file format, past versions may not be compatible
-pub const RATCHET_FILE_VERSION: u8 = 1;
+/// Current version of the ratchet file format, older versions can be upgraded with `ratchet migrate`
+pub const RATCHET_FILE_VERSION: u8 = 2;
 /// Default file name for the ratchet file
 pub const RATCHET_FILE: &str = "ratchet.ron";
 
@@ -19,21 +24,64 @@ pub struct RatchetFile {
 }
 
 pub type RuleName = String;
-// TODO: Probably don't need file name and hash as the key
-pub type RuleMap = BTreeMap<(FileName, FileHash), Problems>;
+/// Every file with problems for a rule, sorted by path
+pub type RuleMap = Vec<FileEntry>;
+
+pub type FileName = String;
+pub type FileHash = u64;
+/// Hash of the matched text and the lines around it, stays the same when unrelated code moves
+pub type Fingerprint = String;
+
+/// The problems a rule found in a single file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileEntry {
+    pub path: FileName,
+    /// Hash of the file's normalized contents when it was checked
+    pub hash: FileHash,
+    pub problems: Vec<Problem>,
+}
 
-type FileName = String;
-type FileHash = u64;
-type Problems = Vec<Problem>;
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Problem {
+    pub start: Position,
+    /// Just past where the problem stops
+    pub end: Position,
+    // TODO: The next two could be optional, rules like regex won't have a unique message
+    pub message: String,
+    pub message_hash: String,
+    pub fingerprint: Fingerprint,
+}
 
-pub type Problem = (Start, End, MessageText, MessageHash);
+/// A 1-based line and column in a file, columns count characters rather than bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Position { line, column }
+    }
+}
+
+/// Only the version of a ratchet file, so it can be checked before the rest is parsed
+#[derive(Deserialize)]
+pub(crate) struct Versioned {
+    pub version: u8,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
 
-/// Start and end are the character positions in the file
-type Start = usize;
-type End = usize;
-// TODO: The next two could be optional, rules like regex won't have a unique message
-type MessageText = String;
-type MessageHash = String;
+impl Default for RatchetFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl RatchetFile {
     pub fn new() -> Self {
@@ -43,184 +91,307 @@ impl RatchetFile {
         }
     }
 
-    pub fn load(file: &String) -> Self {
-        let contents = read_to_string(file);
-
-        if contents.is_err() {
-            println!("No ratchet results file found, evaluating initial baseline");
-            return RatchetFile::new();
+    pub fn load(file: &str) -> Result<Self> {
+        let contents = match read_to_string(file) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                say!("No ratchet results file found, evaluating initial baseline");
+                return Ok(RatchetFile::new());
+            }
+            Err(e) => return Err(RatchetError::io(file, e)),
+        };
+
+        let invalid = |source| RatchetError::InvalidRatchetFile {
+            path: file.into(),
+            source: Box::new(source),
+        };
+
+        let Versioned { version } = ron::de::from_str(&contents).map_err(invalid)?;
+        if version != RATCHET_FILE_VERSION {
+            return Err(RatchetError::UnsupportedRatchetFileVersion {
+                path: file.into(),
+                version,
+            });
         }
 
-        ron::de::from_str(&contents.unwrap())
-            .expect("Failed to deserialize, RON file may be corrupt or an old version")
+        ron::de::from_str(&contents).map_err(invalid)
     }
 
-    pub fn save(&self, file: &String) {
-        let pretty_config = PrettyConfig::new()
-            .depth_limit(4)
-            .separate_tuple_members(true)
-            .enumerate_arrays(true);
-        let ron = ron::ser::to_string_pretty(self, pretty_config).expect("Serialization failed");
+    pub fn save(&self, file: &str) -> Result<()> {
+        let pretty_config = PrettyConfig::new().depth_limit(5).enumerate_arrays(true);
+        let ron = ron::ser::to_string_pretty(self, pretty_config)
+            .map_err(RatchetError::SerializeRatchetFile)?;
 
-        let mut file = File::create(file).expect("Failed to create file");
-        file.write_all(ron.as_bytes())
-            .expect("Failed to write to file");
+        fs::write(file, ron).map_err(|e| RatchetError::io(file, e))
     }
 
-    /// Compare the current ratchet file to a new one
-    /// Returns true if the new ratchet file is worse
-    pub fn compare(&self, new_ratchet: &Self) -> bool {
-        let mut got_worse = false;
-
-        // for each rule, see if it got better or worse than the previous
-        for (rule, previous_rule_items) in &self.rules {
-            let mut previous_rule_count = 0;
-            let mut new_rule_count = 0;
-
-            let new_rule_items = new_ratchet.rules.get(rule);
-
-            if let Some(new_rule_items) = new_rule_items {
-                let (new_count, old_count) = compare_rule_maps(previous_rule_items, new_rule_items);
-                previous_rule_count = old_count;
-                new_rule_count = new_count;
-            }
-
-            got_worse = new_rule_count > previous_rule_count;
-            match new_rule_count.cmp(&previous_rule_count) {
-                Ordering::Greater => {
-                    println!(
-                        "❌ Rule {} got worse ({} new issues out of {} total)",
-                        rule,
-                        new_rule_count - previous_rule_count,
-                        new_rule_count
-                    );
-                }
-                Ordering::Less => {
-                    println!(
-                        "🛠️ Rule {} improved ({} issues fixed out of {} total)",
-                        rule,
-                        previous_rule_count - new_rule_count,
-                        new_rule_count
-                    );
-                }
-                Ordering::Equal => {
-                    println!("✔️ Rule {rule} did not change ({new_rule_count} total)");
-                }
-            }
-        }
-
-        got_worse
+    /// Compare the current ratchet file to a new one, reporting how every rule changed
+    pub fn compare(&self, new_ratchet: &Self) -> CompareReport {
+        CompareReport::new(&self.rules, &new_ratchet.rules)
     }
 }
 
-fn compare_rule_maps(old_map: &RuleMap, new_map: &RuleMap) -> (usize, usize) {
-    let mut total_old_issues = 0;
-    let mut total_new_issues = 0;
-
-    // Step 1: Iterate through the old map
-    for (key, old_problems) in old_map {
-        if let Some(new_problems) = new_map.get(key) {
-            // Key exists in both maps, count issues from both
-            total_old_issues += old_problems.len();
-            total_new_issues += new_problems.len();
-        } else {
-            // Key exists only in the old map
-            total_old_issues += old_problems.len();
+#[cfg(test)]
+mod test {
+    use super::{FileEntry, Position, Problem};
+    use crate::{report::RuleStatus, rules::rule::Severity};
+
+    const TEST_RULE_ONE: &str = "test_rule1";
+
+    fn problem(column: usize, message: &str, fingerprint: &str) -> Problem {
+        Problem {
+            start: Position::new(1, column),
+            end: Position::new(1, column + 1),
+            message: message.into(),
+            message_hash: "hash".into(),
+            fingerprint: fingerprint.into(),
         }
     }
 
-    // Step 2: Iterate through the new map
-    for (key, new_problems) in new_map {
-        if !old_map.contains_key(key) {
-            // Key exists only in the new map
-            total_new_issues += new_problems.len();
+    fn file_entry(path: &str, hash: u64, problems: Vec<Problem>) -> FileEntry {
+        FileEntry {
+            path: path.into(),
+            hash,
+            problems,
         }
     }
 
-    // Step 3: Return totals so we can log properly and report if it got worse
-    (total_new_issues, total_old_issues)
-}
-
-#[cfg(test)]
-mod test {
-    const TEST_RULE_ONE: &str = "test_rule1";
-
     #[test]
     fn new_violation_returns_worse() {
-        let mut previous_rule_issues = super::RuleMap::new();
-        previous_rule_issues.insert(
-            ("file1".into(), 1234),
-            vec![(1, 2, "message1".into(), "hash1".into())],
-        );
+        let previous_rule_issues = vec![file_entry(
+            "file1",
+            1234,
+            vec![problem(1, "message1", "fingerprint1")],
+        )];
 
         let mut previous_file = super::RatchetFile::new();
         previous_file
             .rules
             .insert(TEST_RULE_ONE.into(), previous_rule_issues);
 
-        let mut new_rule_issues = super::RuleMap::new();
-        new_rule_issues.insert(
+        let new_rule_issues = vec![file_entry(
+            "file1",
             // Note the fake hash, it's different because the file "changed"
-            ("file1".into(), 4321),
+            4321,
             vec![
-                (1, 2, "message1".into(), "hash1".into()),
-                (3, 4, "message2".into(), "hash2".into()),
+                problem(1, "message1", "fingerprint1"),
+                problem(3, "message2", "fingerprint2"),
             ],
-        );
+        )];
         let mut new_file = super::RatchetFile::new();
         new_file.rules.insert(TEST_RULE_ONE.into(), new_rule_issues);
 
-        assert!(previous_file.compare(&new_file));
+        assert!(previous_file.compare(&new_file).got_worse());
     }
 
     #[test]
     fn new_improvement_returns_better() {
-        let mut previous_rule_issues = super::RuleMap::new();
-        previous_rule_issues.insert(
-            ("file1".into(), 1234),
+        let previous_rule_issues = vec![file_entry(
+            "file1",
+            1234,
             vec![
-                (1, 2, "message".into(), "hash".into()),
-                (3, 4, "message2".into(), "hash".into()),
+                problem(1, "message", "fingerprint1"),
+                problem(3, "message2", "fingerprint2"),
             ],
-        );
+        )];
 
         let mut previous_file = super::RatchetFile::new();
         previous_file
             .rules
             .insert(TEST_RULE_ONE.into(), previous_rule_issues);
 
-        let mut new_rule_issues = super::RuleMap::new();
-        new_rule_issues.insert(
-            ("file1".into(), 4321),
-            vec![(1, 2, "message1".into(), "hash".into())],
-        );
+        let new_rule_issues = vec![file_entry(
+            "file1",
+            4321,
+            vec![problem(1, "message1", "fingerprint1")],
+        )];
         let mut new_file = super::RatchetFile::new();
         new_file.rules.insert(TEST_RULE_ONE.into(), new_rule_issues);
 
-        assert!(!previous_file.compare(&new_file));
+        assert!(!previous_file.compare(&new_file).got_worse());
     }
 
     #[test]
     fn file_rename_no_changes_returns_same() {
-        let mut previous_rule_issues = super::RuleMap::new();
-        previous_rule_issues.insert(
-            ("file1".into(), 1234),
-            vec![(1, 2, "message".into(), "hash".into())],
-        );
+        let previous_rule_issues = vec![file_entry(
+            "file1",
+            1234,
+            vec![problem(1, "message", "fingerprint")],
+        )];
 
         let mut previous_file = super::RatchetFile::new();
         previous_file
             .rules
             .insert(TEST_RULE_ONE.into(), previous_rule_issues);
 
-        let mut new_rule_issues = super::RuleMap::new();
-        new_rule_issues.insert(
-            ("file1_renamed".into(), 1234),
-            vec![(1, 2, "message".into(), "hash".into())],
-        );
+        let new_rule_issues = vec![file_entry(
+            "file1_renamed",
+            1234,
+            vec![problem(1, "message", "fingerprint")],
+        )];
+        let mut new_file = super::RatchetFile::new();
+        new_file.rules.insert(TEST_RULE_ONE.into(), new_rule_issues);
+
+        assert!(!previous_file.compare(&new_file).got_worse());
+    }
+
+    #[test]
+    fn any_worse_rule_returns_worse() {
+        let worse_rule_issues = vec![file_entry(
+            "file1",
+            1234,
+            vec![problem(1, "message", "fingerprint")],
+        )];
+
+        let mut previous_file = super::RatchetFile::new();
+        previous_file.rules.insert("a_rule".into(), vec![]);
+        previous_file
+            .rules
+            .insert("b_rule".into(), worse_rule_issues.clone());
+
+        // The rule that got worse sorts first, so a later unchanged rule can't hide it
+        let mut new_file = super::RatchetFile::new();
+        new_file
+            .rules
+            .insert("a_rule".into(), worse_rule_issues.clone());
+        new_file.rules.insert("b_rule".into(), worse_rule_issues);
+
+        let report = previous_file.compare(&new_file);
+        assert!(report.got_worse());
+        assert_eq!(report.rules["a_rule"].status, RuleStatus::Worsened);
+        assert_eq!(report.rules["b_rule"].status, RuleStatus::Unchanged);
+    }
+
+    #[test]
+    fn added_and_removed_rules_are_reported() {
+        let rule_issues = vec![file_entry(
+            "file1",
+            1234,
+            vec![problem(1, "message", "fingerprint")],
+        )];
+
+        let mut previous_file = super::RatchetFile::new();
+        previous_file
+            .rules
+            .insert("old_rule".into(), rule_issues.clone());
+
+        let mut new_file = super::RatchetFile::new();
+        new_file.rules.insert("new_rule".into(), rule_issues);
+
+        let report = previous_file.compare(&new_file);
+        assert!(!report.got_worse());
+        assert_eq!(report.rules["old_rule"].status, RuleStatus::Removed);
+        assert_eq!(report.rules["new_rule"].status, RuleStatus::Added);
+        assert_eq!(report.rules["new_rule"].files["file1"].new_count, 1);
+    }
+
+    #[test]
+    fn fingerprints_name_new_and_fixed_problems() {
+        let previous_rule_issues = vec![file_entry(
+            "file1",
+            1234,
+            vec![
+                problem(1, "message", "kept"),
+                problem(3, "message", "fixed"),
+            ],
+        )];
+
+        let mut previous_file = super::RatchetFile::new();
+        previous_file
+            .rules
+            .insert(TEST_RULE_ONE.into(), previous_rule_issues);
+
+        // Positions, file hash, and file name all changed, but the kept problem's fingerprint didn't
+        let new_rule_issues = vec![file_entry(
+            "file1_renamed",
+            4321,
+            vec![
+                problem(10, "message", "kept"),
+                problem(30, "message", "new"),
+            ],
+        )];
         let mut new_file = super::RatchetFile::new();
         new_file.rules.insert(TEST_RULE_ONE.into(), new_rule_issues);
 
-        assert!(!previous_file.compare(&new_file));
+        let report = previous_file.compare(&new_file);
+        let rule_report = &report.rules[TEST_RULE_ONE];
+        assert_eq!(rule_report.status, RuleStatus::Unchanged);
+        assert_eq!(rule_report.new_problems.len(), 1);
+        assert_eq!(rule_report.new_problems[0].0, "file1_renamed");
+        assert_eq!(rule_report.new_problems[0].1.fingerprint, "new");
+        assert_eq!(rule_report.fixed_problems.len(), 1);
+        assert_eq!(rule_report.fixed_problems[0].0, "file1");
+        assert_eq!(rule_report.fixed_problems[0].1.fingerprint, "fixed");
+    }
+
+    #[test]
+    fn old_versions_are_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("ratchet-old-version-{}.ron", std::process::id()));
+        std::fs::write(&path, "(version: 1, rules: {})").unwrap();
+
+        let result = super::RatchetFile::load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(crate::RatchetError::UnsupportedRatchetFileVersion { version: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn warnings_do_not_make_things_worse() {
+        let mut previous_file = super::RatchetFile::new();
+        previous_file.rules.insert(TEST_RULE_ONE.into(), vec![]);
+
+        let mut new_file = super::RatchetFile::new();
+        new_file.rules.insert(
+            TEST_RULE_ONE.into(),
+            vec![file_entry(
+                "file1",
+                1234,
+                vec![problem(1, "message", "fingerprint")],
+            )],
+        );
+
+        let mut severities = std::collections::BTreeMap::new();
+        severities.insert(TEST_RULE_ONE.to_string(), Severity::Warn);
+
+        let report = previous_file.compare(&new_file);
+        assert!(report.got_worse());
+
+        let report = report.with_severities(&severities);
+        assert!(!report.got_worse());
+        assert_eq!(report.warnings().count(), 1);
+    }
+
+    #[test]
+    fn moved_problems_are_stale() {
+        let mut previous_file = super::RatchetFile::new();
+        previous_file.rules.insert(
+            TEST_RULE_ONE.into(),
+            vec![file_entry(
+                "file1",
+                1234,
+                vec![problem(1, "message", "fingerprint")],
+            )],
+        );
+
+        let mut new_file = super::RatchetFile::new();
+        new_file.rules.insert(
+            TEST_RULE_ONE.into(),
+            vec![file_entry(
+                "file1",
+                4321,
+                vec![problem(5, "message", "fingerprint")],
+            )],
+        );
+
+        assert!(!previous_file.compare(&previous_file).is_stale());
+
+        let report = previous_file.compare(&new_file);
+        assert!(!report.got_worse());
+        assert!(report.is_stale());
+        assert_eq!(report.rules[TEST_RULE_ONE].stale_files, vec!["file1"]);
     }
 }
@@ -0,0 +1,254 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+};
+
+use crate::{
+    ratchet_file::{FileEntry, FileName, Fingerprint, Position, Problem, RuleMap, RuleName},
+    rules::rule::Severity,
+};
+
+/// The result of comparing a previous ratchet file against a newly computed one
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompareReport {
+    pub rules: BTreeMap<RuleName, RuleReport>,
+}
+
+/// How a single rule changed between two ratchet files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleReport {
+    pub status: RuleStatus,
+    pub severity: Severity,
+    pub previous_count: usize,
+    pub new_count: usize,
+    /// Every file that had problems for the rule in either ratchet file
+    pub files: BTreeMap<FileName, FileDelta>,
+    /// Every problem the rule has in the new ratchet file, including ones that were already there
+    pub problems: Vec<(FileName, Problem)>,
+    /// Problems whose fingerprint didn't exist before
+    pub new_problems: Vec<(FileName, Problem)>,
+    /// Problems whose fingerprint no longer exists
+    pub fixed_problems: Vec<(FileName, Problem)>,
+    /// Files whose results differ at all between the two ratchet files, even if only a position moved
+    pub stale_files: Vec<FileName>,
+    /// Where each new problem is, so reporters can look problems up without scanning `new_problems`
+    new_keys: HashSet<(FileName, Position, Fingerprint)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleStatus {
+    /// The rule only exists in the new ratchet file, so this is its initial baseline
+    Added,
+    /// The rule only exists in the previous ratchet file
+    Removed,
+    Improved,
+    Worsened,
+    Unchanged,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileDelta {
+    pub previous_count: usize,
+    pub new_count: usize,
+}
+
+impl CompareReport {
+    /// Builds the report for every rule found in either the previous or new rules
+    pub fn new(previous: &BTreeMap<RuleName, RuleMap>, new: &BTreeMap<RuleName, RuleMap>) -> Self {
+        let rule_names = previous.keys().chain(new.keys());
+
+        let rules = rule_names
+            .map(|rule| {
+                let report = RuleReport::new(previous.get(rule), new.get(rule));
+                (rule.clone(), report)
+            })
+            .collect();
+
+        CompareReport { rules }
+    }
+
+    /// Sets the severity of each rule from the config, rules not in the config stay errors
+    pub fn with_severities(mut self, severities: &BTreeMap<RuleName, Severity>) -> Self {
+        for (rule, report) in self.rules.iter_mut() {
+            if let Some(severity) = severities.get(rule) {
+                report.severity = *severity;
+            }
+        }
+        self
+    }
+
+    /// True if any rule with an error severity has more problems than it used to
+    pub fn got_worse(&self) -> bool {
+        self.worsened()
+            .any(|(_, report)| report.severity == Severity::Error)
+    }
+
+    /// True if saving the new ratchet file would change it in any way
+    pub fn is_stale(&self) -> bool {
+        self.stale().next().is_some()
+    }
+
+    /// Rules whose results differ from the previous ratchet file, in either direction
+    pub fn stale(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.rules.iter().filter(|(_, report)| report.is_stale())
+    }
+
+    /// Rules that got worse but are only warnings, so they don't fail anything
+    pub fn warnings(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.worsened()
+            .filter(|(_, report)| report.severity == Severity::Warn)
+    }
+
+    pub fn added(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.with_status(RuleStatus::Added)
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.with_status(RuleStatus::Removed)
+    }
+
+    pub fn improved(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.with_status(RuleStatus::Improved)
+    }
+
+    pub fn worsened(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.with_status(RuleStatus::Worsened)
+    }
+
+    pub fn unchanged(&self) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.with_status(RuleStatus::Unchanged)
+    }
+
+    fn with_status(&self, status: RuleStatus) -> impl Iterator<Item = (&RuleName, &RuleReport)> {
+        self.rules
+            .iter()
+            .filter(move |(_, report)| report.status == status)
+    }
+}
+
+impl RuleReport {
+    fn new(previous: Option<&RuleMap>, new: Option<&RuleMap>) -> Self {
+        let mut files: BTreeMap<FileName, FileDelta> = BTreeMap::new();
+
+        for entry in previous.into_iter().flatten() {
+            files.entry(entry.path.clone()).or_default().previous_count += entry.problems.len();
+        }
+        for entry in new.into_iter().flatten() {
+            files.entry(entry.path.clone()).or_default().new_count += entry.problems.len();
+        }
+
+        let previous_count: usize = files.values().map(|delta| delta.previous_count).sum();
+        let new_count: usize = files.values().map(|delta| delta.new_count).sum();
+
+        let status = match (previous.is_some(), new.is_some()) {
+            (false, _) => RuleStatus::Added,
+            (true, false) => RuleStatus::Removed,
+            (true, true) => match new_count.cmp(&previous_count) {
+                Ordering::Greater => RuleStatus::Worsened,
+                Ordering::Less => RuleStatus::Improved,
+                Ordering::Equal => RuleStatus::Unchanged,
+            },
+        };
+
+        let problems = new
+            .into_iter()
+            .flatten()
+            .flat_map(|entry| {
+                let path = &entry.path;
+                entry
+                    .problems
+                    .iter()
+                    .map(move |problem| (path.clone(), problem.clone()))
+            })
+            .collect();
+        let stale_files = stale_files(previous, new);
+        let new_problems = unmatched_problems(new, previous);
+        let fixed_problems = unmatched_problems(previous, new);
+        let new_keys = new_problems
+            .iter()
+            .map(|(path, problem)| (path.clone(), problem.start, problem.fingerprint.clone()))
+            .collect();
+
+        RuleReport {
+            status,
+            severity: Severity::Error,
+            previous_count,
+            new_count,
+            files,
+            problems,
+            new_problems,
+            fixed_problems,
+            stale_files,
+            new_keys,
+        }
+    }
+
+    /// True if the problem wasn't in the previous ratchet file, as opposed to being part of the baseline
+    pub fn is_new(&self, path: &str, problem: &Problem) -> bool {
+        self.new_keys
+            .contains(&(path.to_string(), problem.start, problem.fingerprint.clone()))
+    }
+
+    /// True if the rule's results in the ratchet file would change at all
+    pub fn is_stale(&self) -> bool {
+        matches!(self.status, RuleStatus::Added | RuleStatus::Removed)
+            || !self.stale_files.is_empty()
+    }
+}
+
+/// Finds every file whose entry isn't exactly the same in both rule maps
+fn stale_files(previous: Option<&RuleMap>, new: Option<&RuleMap>) -> Vec<FileName> {
+    let previous: BTreeMap<&FileName, &FileEntry> = previous
+        .into_iter()
+        .flatten()
+        .map(|entry| (&entry.path, entry))
+        .collect();
+    let new: BTreeMap<&FileName, &FileEntry> = new
+        .into_iter()
+        .flatten()
+        .map(|entry| (&entry.path, entry))
+        .collect();
+
+    let paths: BTreeSet<&&FileName> = previous.keys().chain(new.keys()).collect();
+    paths
+        .into_iter()
+        .filter(|path| previous.get(*path) != new.get(*path))
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Finds every problem in `rule_map` that has no matching fingerprint in `other`.
+/// Fingerprints are matched across the whole rule instead of per file so renamed files still match,
+/// and they're counted so duplicate lines only match as many times as they appear.
+fn unmatched_problems(
+    rule_map: Option<&RuleMap>,
+    other: Option<&RuleMap>,
+) -> Vec<(FileName, Problem)> {
+    let mut remaining: HashMap<&Fingerprint, usize> = HashMap::new();
+    for problem in other
+        .into_iter()
+        .flatten()
+        .flat_map(|entry| &entry.problems)
+    {
+        *remaining.entry(&problem.fingerprint).or_default() += 1;
+    }
+
+    let mut unmatched = Vec::new();
+    for entry in rule_map.into_iter().flatten() {
+        for problem in &entry.problems {
+            match remaining.get_mut(&problem.fingerprint) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => unmatched.push((entry.path.clone(), problem.clone())),
+            }
+        }
+    }
+
+    unmatched
+}
+
+impl FileDelta {
+    /// True if the file has more problems than it used to
+    pub fn got_worse(&self) -> bool {
+        self.new_count > self.previous_count
+    }
+}
@@ -0,0 +1,92 @@
+//! Checkstyle XML, for older tools like Jenkins warnings plugins and Sonar importers.
+//! Every problem is reported with its rule's severity and the rule name as the source.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    error::Result,
+    ratchet_file::{FileName, Problem, RuleName},
+    report::CompareReport,
+    reporters::xml_escape,
+    rules::rule::Severity,
+};
+
+const CHECKSTYLE_VERSION: &str = "4.3";
+
+/// Renders every problem grouped by file
+pub fn render(report: &CompareReport) -> Result<String> {
+    let mut files: BTreeMap<&FileName, Vec<(&RuleName, &'static str, &Problem)>> = BTreeMap::new();
+    for (rule, rule_report) in &report.rules {
+        let severity = match rule_report.severity {
+            Severity::Error => "error",
+            Severity::Warn => "warning",
+            Severity::Off => continue,
+        };
+
+        for (path, problem) in &rule_report.problems {
+            files
+                .entry(path)
+                .or_default()
+                .push((rule, severity, problem));
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<checkstyle version=\"{CHECKSTYLE_VERSION}\">");
+    for (path, mut problems) in files {
+        problems.sort_by_key(|(_, _, problem)| problem.start);
+
+        let _ = writeln!(xml, "  <file name=\"{}\">", xml_escape(path));
+        for (rule, severity, problem) in problems {
+            let _ = writeln!(
+                xml,
+                "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"{}\"/>",
+                problem.start.line,
+                problem.start.column,
+                severity,
+                xml_escape(&problem.message),
+                xml_escape(rule)
+            );
+        }
+        xml.push_str("  </file>\n");
+    }
+    xml.push_str("</checkstyle>\n");
+
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        reporters::test::{TEST_RULE, sample_report},
+        rules::rule::Severity,
+    };
+
+    #[test]
+    fn groups_problems_by_file() {
+        let xml = super::render(&sample_report()).unwrap();
+
+        assert_eq!(
+            xml,
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <checkstyle version=\"4.3\">\n  \
+                 <file name=\"src/lib.rs\">\n    \
+                 <error line=\"2\" column=\"5\" severity=\"error\" message=\"widget\" source=\"{TEST_RULE}\"/>\n    \
+                 <error line=\"7\" column=\"5\" severity=\"error\" message=\"widget\" source=\"{TEST_RULE}\"/>\n    \
+                 <error line=\"9\" column=\"5\" severity=\"error\" message=\"widget\" source=\"{TEST_RULE}\"/>\n  \
+                 </file>\n\
+                 </checkstyle>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn off_rules_are_left_out() {
+        let mut report = sample_report();
+        report.rules.get_mut(TEST_RULE).unwrap().severity = Severity::Off;
+
+        let xml = super::render(&report).unwrap();
+        assert!(!xml.contains("<file"));
+    }
+}
@@ -0,0 +1,180 @@
+//! GitHub Actions workflow commands. New problems become `::error` annotations, or `::warning` for rules
+//! that only warn, so they show up inline on the pull request. When `GITHUB_STEP_SUMMARY` is set,
+//! a markdown table of every rule is also added to the job summary.
+
+use std::{env, fmt::Write as _, fs::OpenOptions, io::Write as _, path::Path};
+
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::{FileName, Problem, RuleName},
+    report::{CompareReport, RuleStatus},
+    reporters::RunInfo,
+    rules::rule::Severity,
+};
+
+/// Environment variable GitHub Actions sets to the file backing the job summary
+pub const GITHUB_STEP_SUMMARY: &str = "GITHUB_STEP_SUMMARY";
+
+/// Renders an annotation for every new problem
+pub fn render(report: &CompareReport) -> String {
+    let mut commands = String::new();
+    for (rule, rule_report) in &report.rules {
+        let command = match rule_report.severity {
+            Severity::Error => "error",
+            Severity::Warn => "warning",
+            Severity::Off => continue,
+        };
+
+        for (path, problem) in &rule_report.new_problems {
+            let _ = writeln!(commands, "{}", annotation(command, rule, path, problem));
+        }
+    }
+    commands
+}
+
+/// Appends the summary table to the file in `GITHUB_STEP_SUMMARY`, does nothing outside of GitHub Actions
+pub fn write_step_summary(report: &CompareReport, run: &RunInfo) -> Result<()> {
+    match env::var_os(GITHUB_STEP_SUMMARY) {
+        Some(path) if !path.is_empty() => append_step_summary(Path::new(&path), report, run),
+        _ => Ok(()),
+    }
+}
+
+fn append_step_summary(path: &Path, report: &CompareReport, run: &RunInfo) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|error| RatchetError::io(path, error))?;
+
+    file.write_all(step_summary(report, run).as_bytes())
+        .map_err(|error| RatchetError::io(path, error))
+}
+
+/// Markdown table with a row per rule
+pub fn step_summary(report: &CompareReport, run: &RunInfo) -> String {
+    let mut summary = format!("### Ratchet {}\n\n", run.command);
+    summary.push_str("| Rule | Status | Previous | Current | Change |\n");
+    summary.push_str("| ---- | ------ | -------: | ------: | -----: |\n");
+
+    for (rule, rule_report) in &report.rules {
+        let status = match (rule_report.severity, rule_report.status) {
+            (Severity::Off, _) => "💤 Off",
+            (_, RuleStatus::Added) => "🆕 Added",
+            (_, RuleStatus::Removed) => "🗑️ Removed",
+            (Severity::Warn, RuleStatus::Worsened) => "⚠️ Worse",
+            (_, RuleStatus::Worsened) => "❌ Worse",
+            (_, RuleStatus::Improved) => "🛠️ Improved",
+            (_, RuleStatus::Unchanged) => "✔️ Unchanged",
+        };
+        let change = rule_report.new_count as i64 - rule_report.previous_count as i64;
+
+        let _ = writeln!(
+            summary,
+            "| {} | {} | {} | {} | {:+} |",
+            rule.replace('|', "\\|"),
+            status,
+            rule_report.previous_count,
+            rule_report.new_count,
+            change
+        );
+    }
+
+    summary.push('\n');
+    summary
+}
+
+fn annotation(command: &str, rule: &RuleName, path: &FileName, problem: &Problem) -> String {
+    format!(
+        "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+        command,
+        escape_property(path),
+        problem.start.line,
+        problem.start.column,
+        problem.end.line,
+        problem.end.column,
+        escape_property(&format!("Ratchet: {rule}")),
+        escape_data(&format!("New problem for {rule} ({})", problem.message))
+    )
+}
+
+/// Workflow command messages end at a newline, so they have to be percent encoded
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Properties are also split on `,` and `:`
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, time::Duration};
+
+    use crate::{
+        reporters::{
+            RunInfo,
+            test::{TEST_RULE, sample_report},
+        },
+        rules::rule::Severity,
+    };
+
+    fn run() -> RunInfo<'static> {
+        RunInfo {
+            command: "check",
+            duration: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn annotates_new_problems() {
+        let commands = super::render(&sample_report());
+
+        assert_eq!(
+            commands,
+            format!(
+                "::error file=src/lib.rs,line=7,col=5,endLine=7,endColumn=11,title=Ratchet%3A {TEST_RULE}::New problem for {TEST_RULE} (widget)\n\
+                 ::error file=src/lib.rs,line=9,col=5,endLine=9,endColumn=11,title=Ratchet%3A {TEST_RULE}::New problem for {TEST_RULE} (widget)\n"
+            )
+        );
+    }
+
+    #[test]
+    fn warnings_use_warning_command() {
+        let mut report = sample_report();
+        report.rules.get_mut(TEST_RULE).unwrap().severity = Severity::Warn;
+        assert!(super::render(&report).starts_with("::warning file=src/lib.rs,"));
+
+        report.rules.get_mut(TEST_RULE).unwrap().severity = Severity::Off;
+        assert_eq!(super::render(&report), "");
+    }
+
+    #[test]
+    fn escapes_workflow_commands() {
+        assert_eq!(super::escape_data("50%\nmore"), "50%25%0Amore");
+        assert_eq!(super::escape_property("a:b,c"), "a%3Ab%2Cc");
+    }
+
+    #[test]
+    fn appends_step_summary() {
+        let path = env::temp_dir().join(format!("ratchet-step-summary-{}.md", std::process::id()));
+        fs::write(&path, "Earlier step\n").unwrap();
+
+        super::append_step_summary(&path, &sample_report(), &run()).unwrap();
+        let summary = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            summary,
+            format!(
+                "Earlier step\n### Ratchet check\n\n\
+                 | Rule | Status | Previous | Current | Change |\n\
+                 | ---- | ------ | -------: | ------: | -----: |\n\
+                 | {TEST_RULE} | ❌ Worse | 2 | 3 | +1 |\n\n"
+            )
+        );
+    }
+}
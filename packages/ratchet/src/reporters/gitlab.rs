@@ -0,0 +1,132 @@
+//! GitLab Code Quality, the Code Climate subset GitLab reads for its merge request widget.
+//! Problems in the previous ratchet file are reported with an `info` severity so only new ones stand out.
+
+use std::{collections::HashMap, hash::Hasher};
+
+use seahash::SeaHasher;
+use serde::Serialize;
+
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::{FileName, Problem, RuleName},
+    report::{CompareReport, RuleReport},
+    rules::rule::Severity,
+};
+
+#[derive(Serialize)]
+struct CodeQualityIssue<'a> {
+    description: String,
+    check_name: &'a RuleName,
+    fingerprint: String,
+    severity: &'static str,
+    location: Location<'a>,
+}
+
+#[derive(Serialize)]
+struct Location<'a> {
+    path: &'a FileName,
+    lines: Lines,
+}
+
+#[derive(Serialize)]
+struct Lines {
+    begin: usize,
+    end: usize,
+}
+
+/// Renders every problem as a Code Quality issue
+pub fn render(report: &CompareReport) -> Result<String> {
+    let mut issues = Vec::new();
+    // GitLab needs fingerprints to be unique, ratchet's repeat when the same line shows up twice
+    let mut occurrences: HashMap<(&RuleName, &str), usize> = HashMap::new();
+
+    for (rule, rule_report) in &report.rules {
+        if rule_report.severity == Severity::Off {
+            continue;
+        }
+
+        for (path, problem) in &rule_report.problems {
+            let occurrence = occurrences
+                .entry((rule, problem.fingerprint.as_str()))
+                .or_default();
+            *occurrence += 1;
+
+            issues.push(issue(rule, rule_report, path, problem, *occurrence));
+        }
+    }
+
+    serde_json::to_string_pretty(&issues).map_err(RatchetError::SerializeReport)
+}
+
+fn issue<'a>(
+    rule: &'a RuleName,
+    report: &RuleReport,
+    path: &'a FileName,
+    problem: &Problem,
+    occurrence: usize,
+) -> CodeQualityIssue<'a> {
+    let is_new = report.is_new(path, problem);
+    let (severity, state) = match (is_new, report.severity) {
+        (false, _) => ("info", "baseline"),
+        (true, Severity::Warn) => ("minor", "new"),
+        (true, _) => ("major", "new"),
+    };
+
+    let mut hasher = SeaHasher::new();
+    hasher.write(rule.as_bytes());
+    hasher.write(problem.fingerprint.as_bytes());
+    hasher.write_usize(occurrence);
+
+    CodeQualityIssue {
+        description: format!("{rule}: {} ({state})", problem.message),
+        check_name: rule,
+        fingerprint: format!("{:016x}", hasher.finish()),
+        severity,
+        location: Location {
+            path,
+            lines: Lines {
+                begin: problem.start.line,
+                end: problem.end.line,
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use serde_json::{Value, json};
+
+    use crate::reporters::test::{TEST_RULE, sample_report};
+
+    #[test]
+    fn marks_baseline_and_new_problems() {
+        let rendered = super::render(&sample_report()).unwrap();
+        let issues: Value = serde_json::from_str(&rendered).unwrap();
+        let issues = issues.as_array().unwrap();
+
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0]["severity"], "info");
+        assert_eq!(
+            issues[0]["description"],
+            format!("{TEST_RULE}: widget (baseline)")
+        );
+        assert_eq!(issues[1]["severity"], "major");
+        assert_eq!(
+            issues[1]["description"],
+            format!("{TEST_RULE}: widget (new)")
+        );
+        assert_eq!(issues[1]["check_name"], TEST_RULE);
+        assert_eq!(
+            issues[1]["location"],
+            json!({ "path": "src/lib.rs", "lines": { "begin": 7, "end": 7 } })
+        );
+
+        let fingerprints: HashSet<&str> = issues
+            .iter()
+            .map(|issue| issue["fingerprint"].as_str().unwrap())
+            .collect();
+        assert_eq!(fingerprints.len(), 3);
+    }
+}
@@ -0,0 +1,403 @@
+//! A self-contained static site of everything in the ratchet file: an index with per rule totals,
+//! a sortable table of files, and the config for each rule, plus a page per file with its source
+//! and every tracked problem highlighted.
+
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use crate::{
+    config::RatchetConfig,
+    error::{RatchetError, Result},
+    ratchet_file::{FileEntry, FileName, Problem, RatchetFile, RuleName},
+    reporters::xml_escape,
+    rules::{RatchetRule, rule::Severity},
+    utils::to_normalized_file_contents,
+};
+
+const FILES_DIR: &str = "files";
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #1f2328; }
+table { border-collapse: collapse; margin-bottom: 2rem; }
+th, td { border: 1px solid #d0d7de; padding: 0.25rem 0.75rem; text-align: left; }
+th[data-sort] { cursor: pointer; user-select: none; }
+td.count { text-align: right; }
+pre { background: #f6f8fa; padding: 1rem; }
+.source { font-family: ui-monospace, monospace; border: none; }
+.source td { border: none; padding: 0 0.5rem; white-space: pre; }
+.source .line-number { color: #8c959f; text-align: right; }
+.source tr.hit { background: #fff8c5; }
+.source mark { background: #ffb4b4; }
+.note { color: #9a6700; }
+";
+
+/// Sorts a table by the clicked header, numbers sort largest first
+const SORT_SCRIPT: &str = "
+document.querySelectorAll('th[data-sort]').forEach((header) => {
+  header.addEventListener('click', () => {
+    const table = header.closest('table');
+    const body = table.tBodies[0];
+    const column = header.cellIndex;
+    const numeric = header.dataset.sort === 'number';
+    const ascending = header.dataset.direction !== 'asc';
+    header.dataset.direction = ascending ? 'asc' : 'desc';
+    const value = (row) => row.cells[column].dataset.value ?? row.cells[column].textContent;
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const order = numeric ? value(a) - value(b) : value(a).localeCompare(value(b));
+      return ascending === numeric ? -order : order;
+    });
+    rows.forEach((row) => body.appendChild(row));
+  });
+});
+";
+
+/// Every rule's entry for a single file
+type FileRules<'a> = Vec<(&'a RuleName, &'a FileEntry)>;
+
+/// Writes the site into `dir`, reading sources from `root` and showing each rule's config from `config`
+pub fn write_site(
+    dir: &Path,
+    ratchet_file: &RatchetFile,
+    config: &RatchetConfig,
+    config_path: &str,
+    root: &Path,
+) -> Result<()> {
+    let files_dir = dir.join(FILES_DIR);
+    fs::create_dir_all(&files_dir).map_err(|e| RatchetError::io(&files_dir, e))?;
+
+    let mut files: BTreeMap<&FileName, FileRules> = BTreeMap::new();
+    for (rule, entries) in &ratchet_file.rules {
+        for entry in entries {
+            files.entry(&entry.path).or_default().push((rule, entry));
+        }
+    }
+
+    let pages: BTreeMap<&FileName, String> = files
+        .keys()
+        .enumerate()
+        .map(|(index, path)| (*path, format!("{FILES_DIR}/{index}.html")))
+        .collect();
+
+    for (path, file_rules) in &files {
+        let page = dir.join(&pages[path]);
+        let html = file_page(path, file_rules, &root.join(path));
+        write_page(&page, &html)?;
+    }
+
+    let index = dir.join("index.html");
+    write_page(
+        &index,
+        &index_page(ratchet_file, config, config_path, &files, &pages),
+    )
+}
+
+fn write_page(path: &Path, html: &str) -> Result<()> {
+    fs::write(path, html).map_err(|e| RatchetError::io(path, e))
+}
+
+fn index_page(
+    ratchet_file: &RatchetFile,
+    config: &RatchetConfig,
+    config_path: &str,
+    files: &BTreeMap<&FileName, FileRules>,
+    pages: &BTreeMap<&FileName, String>,
+) -> String {
+    let rule_names: Vec<&RuleName> = {
+        let mut names: Vec<&RuleName> = ratchet_file
+            .rules
+            .keys()
+            .chain(config.rules.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+
+    let mut body = String::from("<h1>Ratchet report</h1>\n");
+
+    body.push_str("<h2>Rules</h2>\n<table>\n<thead><tr>");
+    body.push_str("<th data-sort=\"text\">Rule</th><th data-sort=\"text\">Severity</th>");
+    body.push_str("<th data-sort=\"number\">Problems</th><th data-sort=\"number\">Files</th>");
+    body.push_str("</tr></thead>\n<tbody>\n");
+    for rule in &rule_names {
+        let entries = ratchet_file.rules.get(*rule);
+        let problems: usize = entries
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.problems.len())
+            .sum();
+        let file_count = entries.map_or(0, Vec::len);
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"#{}\">{}</a></td><td>{}</td><td class=\"count\">{}</td><td class=\"count\">{}</td></tr>",
+            rule_anchor(rule),
+            xml_escape(rule),
+            severity_name(config.rules.get(*rule)),
+            problems,
+            file_count
+        );
+    }
+    body.push_str("</tbody>\n</table>\n");
+
+    body.push_str("<h2>Files</h2>\n<table>\n<thead><tr><th data-sort=\"text\">File</th>");
+    for rule in &rule_names {
+        let _ = write!(body, "<th data-sort=\"number\">{}</th>", xml_escape(rule));
+    }
+    body.push_str("<th data-sort=\"number\">Total</th></tr></thead>\n<tbody>\n");
+    for (path, file_rules) in files {
+        let _ = write!(
+            body,
+            "<tr><td><a href=\"{}\">{}</a></td>",
+            pages[path],
+            xml_escape(path)
+        );
+        for rule in &rule_names {
+            let count = file_rules
+                .iter()
+                .find(|(name, _)| name == rule)
+                .map_or(0, |(_, entry)| entry.problems.len());
+            let _ = write!(body, "<td class=\"count\">{count}</td>");
+        }
+        let total: usize = file_rules
+            .iter()
+            .map(|(_, entry)| entry.problems.len())
+            .sum();
+        let _ = writeln!(body, "<td class=\"count\">{total}</td></tr>");
+    }
+    body.push_str("</tbody>\n</table>\n");
+
+    let _ = writeln!(
+        body,
+        "<h2>Config</h2>\n<p>Rules as configured in <code>{}</code></p>",
+        xml_escape(config_path)
+    );
+    for rule in &rule_names {
+        let _ = writeln!(
+            body,
+            "<h3 id=\"{}\">{}</h3>",
+            rule_anchor(rule),
+            xml_escape(rule)
+        );
+        match config.rules.get(*rule) {
+            Some(rule_config) => {
+                let _ = writeln!(
+                    body,
+                    "<pre>{}</pre>",
+                    xml_escape(&rule_toml(rule, rule_config))
+                );
+            }
+            None => body.push_str(
+                "<p class=\"note\">This rule is no longer in the config, its results are left from an earlier run</p>\n",
+            ),
+        }
+    }
+
+    page("Ratchet report", &body)
+}
+
+fn file_page(path: &FileName, file_rules: &FileRules, source_path: &Path) -> String {
+    let mut body = format!(
+        "<p><a href=\"../index.html\">Ratchet report</a></p>\n<h1>{}</h1>\n",
+        xml_escape(path)
+    );
+
+    let problems: Vec<(&RuleName, &Problem)> = {
+        let mut problems: Vec<(&RuleName, &Problem)> = file_rules
+            .iter()
+            .flat_map(|(rule, entry)| entry.problems.iter().map(move |problem| (*rule, problem)))
+            .collect();
+        problems.sort_by_key(|(_, problem)| problem.start);
+        problems
+    };
+
+    body.push_str("<table>\n<thead><tr><th>Position</th><th>Rule</th><th>Message</th></tr></thead>\n<tbody>\n");
+    for (rule, problem) in &problems {
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"#L{}\">{}</a></td><td><a href=\"../index.html#{}\">{}</a></td><td>{}</td></tr>",
+            problem.start.line,
+            problem.start,
+            rule_anchor(rule),
+            xml_escape(rule),
+            xml_escape(&problem.message)
+        );
+    }
+    body.push_str("</tbody>\n</table>\n");
+
+    match fs::read_to_string(source_path) {
+        Ok(content) => {
+            let content = to_normalized_file_contents(&content);
+            let hash = seahash::hash(content.as_bytes());
+            if file_rules.iter().any(|(_, entry)| entry.hash != hash) {
+                body.push_str("<p class=\"note\">This file changed since the ratchet was last turned, highlights may be off</p>\n");
+            }
+            body.push_str(&source_table(&content, &problems));
+        }
+        Err(_) => body.push_str("<p class=\"note\">This file no longer exists</p>\n"),
+    }
+
+    page(path, &body)
+}
+
+/// Renders the source with line numbers, marking the characters each problem covers
+fn source_table(content: &str, problems: &[(&RuleName, &Problem)]) -> String {
+    let mut table = String::from("<table class=\"source\">\n");
+
+    for (index, line) in content.split('\n').enumerate() {
+        let line_number = index + 1;
+        let chars: Vec<char> = line.chars().collect();
+        let mut marked = vec![false; chars.len()];
+
+        for (_, problem) in problems {
+            if line_number < problem.start.line || line_number > problem.end.line {
+                continue;
+            }
+            let from = if line_number == problem.start.line {
+                problem.start.column.saturating_sub(1)
+            } else {
+                0
+            };
+            let to = if line_number == problem.end.line {
+                problem.end.column.saturating_sub(1)
+            } else {
+                chars.len()
+            };
+            for mark in marked.iter_mut().take(to).skip(from) {
+                *mark = true;
+            }
+        }
+
+        let mut html = String::new();
+        let mut in_mark = false;
+        for (c, is_marked) in chars.iter().zip(&marked) {
+            if *is_marked != in_mark {
+                html.push_str(if *is_marked { "<mark>" } else { "</mark>" });
+                in_mark = *is_marked;
+            }
+            html.push_str(&xml_escape(&c.to_string()));
+        }
+        if in_mark {
+            html.push_str("</mark>");
+        }
+
+        let hit = problems
+            .iter()
+            .any(|(_, problem)| (problem.start.line..=problem.end.line).contains(&line_number));
+        let _ = writeln!(
+            table,
+            "<tr id=\"L{line_number}\"{}><td class=\"line-number\">{line_number}</td><td>{html}</td></tr>",
+            if hit { " class=\"hit\"" } else { "" }
+        );
+    }
+
+    table.push_str("</table>\n");
+    table
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n{body}<script>{SORT_SCRIPT}</script>\n</body>\n</html>\n",
+        xml_escape(title)
+    )
+}
+
+/// The rule as it would be written in the config file
+fn rule_toml(name: &RuleName, rule: &RatchetRule) -> String {
+    let rules = BTreeMap::from([(name, rule)]);
+    let config = BTreeMap::from([("rules", rules)]);
+    toml::to_string(&config).unwrap_or_default()
+}
+
+fn severity_name(rule: Option<&RatchetRule>) -> &'static str {
+    match rule.map(RatchetRule::severity) {
+        Some(Severity::Error) => "error",
+        Some(Severity::Warn) => "warn",
+        Some(Severity::Off) => "off",
+        None => "not configured",
+    }
+}
+
+/// Anchor for a rule's config, rule names can have spaces and punctuation in them
+fn rule_anchor(rule: &RuleName) -> String {
+    let slug: String = rule
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("rule-{slug}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use crate::{
+        config::RatchetConfig,
+        ratchet_file::{FileEntry, Position, Problem, RatchetFile},
+        rules::{RatchetRule, todo::TodoRule},
+    };
+
+    const TEST_RULE: &str = "No more widgets";
+
+    #[test]
+    fn writes_index_and_file_pages() {
+        let dir = env::temp_dir().join(format!("ratchet-html-{}", std::process::id()));
+        let root = dir.join("root");
+        let site = dir.join("site");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "fn main() {\n    widget(<T>);\n}\n",
+        )
+        .unwrap();
+
+        let mut ratchet_file = RatchetFile::new();
+        ratchet_file.rules.insert(
+            TEST_RULE.into(),
+            vec![FileEntry {
+                path: "src/lib.rs".into(),
+                hash: 0,
+                problems: vec![Problem {
+                    start: Position::new(2, 5),
+                    end: Position::new(2, 11),
+                    message: "widget".into(),
+                    message_hash: "hash".into(),
+                    fingerprint: "fingerprint".into(),
+                }],
+            }],
+        );
+        let mut config = RatchetConfig::new();
+        config.rules.insert(
+            TEST_RULE.into(),
+            RatchetRule::Todo(TodoRule {
+                include: None,
+                exclude: None,
+                severity: Default::default(),
+                pattern_syntax: Default::default(),
+            }),
+        );
+
+        super::write_site(&site, &ratchet_file, &config, "ratchet.toml", &root).unwrap();
+        let index = fs::read_to_string(site.join("index.html")).unwrap();
+        let file_page = fs::read_to_string(site.join("files/0.html")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(index.contains(
+            "<tr><td><a href=\"#rule-no-more-widgets\">No more widgets</a></td><td>error</td><td class=\"count\">1</td><td class=\"count\">1</td></tr>"
+        ));
+        assert!(index.contains("<a href=\"files/0.html\">src/lib.rs</a>"));
+        assert!(index.contains("<h3 id=\"rule-no-more-widgets\">"));
+        assert!(index.contains("type = &quot;Todo&quot;"));
+
+        assert!(file_page.contains("<a href=\"../index.html#rule-no-more-widgets\">"));
+        assert!(file_page.contains(
+            "<tr id=\"L2\" class=\"hit\"><td class=\"line-number\">2</td><td>    <mark>widget</mark>(&lt;T&gt;);</td></tr>"
+        ));
+        // The fake hash doesn't match the file on disk
+        assert!(file_page.contains("This file changed since the ratchet was last turned"));
+    }
+}
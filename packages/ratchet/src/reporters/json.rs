@@ -0,0 +1,197 @@
+//! The JSON report is a single object, fields are only ever added to it so tools can rely on them:
+//!
+//! - `schema_version`: bumped if a field is ever removed or changes meaning
+//! - `command`, `duration_ms`: what ran and how long it took
+//! - `got_worse`, `stale`: the same checks `ratchet check` and `ratchet check --strict` fail on
+//! - `totals`: problem counts summed over every rule
+//! - `rules`: per rule counts, per file counts, and the problems that were added or fixed
+
+use serde::Serialize;
+
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::{FileName, Fingerprint, Position, Problem, RuleName},
+    report::{CompareReport, RuleReport, RuleStatus},
+    reporters::RunInfo,
+    rules::rule::Severity,
+};
+
+pub const JSON_SCHEMA_VERSION: u8 = 1;
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u8,
+    command: &'a str,
+    duration_ms: u128,
+    got_worse: bool,
+    stale: bool,
+    totals: Counts,
+    rules: Vec<JsonRule<'a>>,
+}
+
+#[derive(Serialize)]
+struct Counts {
+    previous_count: usize,
+    new_count: usize,
+    /// Positive when things got worse
+    delta: i64,
+}
+
+#[derive(Serialize)]
+struct JsonRule<'a> {
+    name: &'a RuleName,
+    status: &'static str,
+    severity: Severity,
+    stale: bool,
+    #[serde(flatten)]
+    counts: Counts,
+    files: Vec<JsonFile<'a>>,
+    new_problems: Vec<JsonProblem<'a>>,
+    fixed_problems: Vec<JsonProblem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a FileName,
+    #[serde(flatten)]
+    counts: Counts,
+}
+
+#[derive(Serialize)]
+struct JsonProblem<'a> {
+    path: &'a FileName,
+    start: Position,
+    end: Position,
+    message: &'a str,
+    fingerprint: &'a Fingerprint,
+}
+
+impl Counts {
+    fn new(previous_count: usize, new_count: usize) -> Self {
+        Counts {
+            previous_count,
+            new_count,
+            delta: new_count as i64 - previous_count as i64,
+        }
+    }
+}
+
+/// Renders the report as pretty printed JSON
+pub fn render(report: &CompareReport, run: &RunInfo) -> Result<String> {
+    let rules: Vec<JsonRule> = report
+        .rules
+        .iter()
+        .map(|(name, rule_report)| json_rule(name, rule_report))
+        .collect();
+
+    let previous_count = report.rules.values().map(|rule| rule.previous_count).sum();
+    let new_count = report.rules.values().map(|rule| rule.new_count).sum();
+
+    let json_report = JsonReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        command: run.command,
+        duration_ms: run.duration.as_millis(),
+        got_worse: report.got_worse(),
+        stale: report.is_stale(),
+        totals: Counts::new(previous_count, new_count),
+        rules,
+    };
+
+    serde_json::to_string_pretty(&json_report).map_err(RatchetError::SerializeReport)
+}
+
+fn json_rule<'a>(name: &'a RuleName, report: &'a RuleReport) -> JsonRule<'a> {
+    JsonRule {
+        name,
+        status: status_name(report.status),
+        severity: report.severity,
+        stale: report.is_stale(),
+        counts: Counts::new(report.previous_count, report.new_count),
+        files: report
+            .files
+            .iter()
+            .map(|(path, delta)| JsonFile {
+                path,
+                counts: Counts::new(delta.previous_count, delta.new_count),
+            })
+            .collect(),
+        new_problems: json_problems(&report.new_problems),
+        fixed_problems: json_problems(&report.fixed_problems),
+    }
+}
+
+fn json_problems(problems: &[(FileName, Problem)]) -> Vec<JsonProblem<'_>> {
+    problems
+        .iter()
+        .map(|(path, problem)| JsonProblem {
+            path,
+            start: problem.start,
+            end: problem.end,
+            message: &problem.message,
+            fingerprint: &problem.fingerprint,
+        })
+        .collect()
+}
+
+fn status_name(status: RuleStatus) -> &'static str {
+    match status {
+        RuleStatus::Added => "added",
+        RuleStatus::Removed => "removed",
+        RuleStatus::Improved => "improved",
+        RuleStatus::Worsened => "worsened",
+        RuleStatus::Unchanged => "unchanged",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{Value, json};
+
+    use crate::reporters::{
+        RunInfo,
+        test::{TEST_RULE, sample_report},
+    };
+
+    #[test]
+    fn renders_counts_and_problems() {
+        let run = RunInfo {
+            command: "check",
+            duration: Duration::from_millis(1500),
+        };
+
+        let rendered = super::render(&sample_report(), &run).unwrap();
+        let json: Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["command"], "check");
+        assert_eq!(json["duration_ms"], 1500);
+        assert_eq!(json["got_worse"], true);
+        assert_eq!(
+            json["totals"],
+            json!({ "previous_count": 2, "new_count": 3, "delta": 1 })
+        );
+
+        let rule = &json["rules"][0];
+        assert_eq!(rule["name"], TEST_RULE);
+        assert_eq!(rule["status"], "worsened");
+        assert_eq!(rule["severity"], "error");
+        assert_eq!(rule["delta"], 1);
+        assert_eq!(
+            rule["files"],
+            json!([{ "path": "src/lib.rs", "previous_count": 2, "new_count": 3, "delta": 1 }])
+        );
+        assert_eq!(rule["new_problems"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            rule["fixed_problems"][0],
+            json!({
+                "path": "src/lib.rs",
+                "start": { "line": 4, "column": 5 },
+                "end": { "line": 4, "column": 11 },
+                "message": "widget",
+                "fingerprint": "fixed",
+            })
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! JUnit XML, for CI dashboards. Each rule is a testcase that fails when it got worse,
+//! with the new problems in the failure. Rules that are off are skipped and warnings never fail.
+
+use std::fmt::Write;
+
+use crate::{
+    error::Result,
+    ratchet_file::RuleName,
+    report::{CompareReport, RuleReport, RuleStatus},
+    reporters::{RunInfo, xml_escape},
+    rules::rule::Severity,
+};
+
+/// Renders the report as a JUnit XML document with a single test suite
+pub fn render(report: &CompareReport, run: &RunInfo) -> Result<String> {
+    let tests = report.rules.len();
+    let failures = report
+        .rules
+        .values()
+        .filter(|rule| is_failure(rule))
+        .count();
+    let skipped = report
+        .rules
+        .values()
+        .filter(|rule| rule.severity == Severity::Off)
+        .count();
+    let time = run.duration.as_secs_f32();
+    let suite_name = xml_escape(&format!("ratchet {}", run.command));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"ratchet\" tests=\"{tests}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">"
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{suite_name}\" tests=\"{tests}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">"
+    );
+    for (name, rule_report) in &report.rules {
+        write_testcase(&mut xml, name, rule_report);
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+
+    Ok(xml)
+}
+
+fn write_testcase(xml: &mut String, name: &RuleName, report: &RuleReport) {
+    let name = xml_escape(name);
+    let _ = write!(xml, "    <testcase name=\"{name}\" classname=\"ratchet\"");
+
+    if report.severity == Severity::Off {
+        xml.push_str(">\n      <skipped message=\"Rule is off\"/>\n    </testcase>\n");
+    } else if report.status == RuleStatus::Worsened {
+        let summary = format!(
+            "Rule got worse ({} new issues out of {} total)",
+            report.new_count - report.previous_count,
+            report.new_count
+        );
+        let problems: String = report
+            .new_problems
+            .iter()
+            .map(|(path, problem)| format!("{path}:{} ({})\n", problem.start, problem.message))
+            .collect();
+
+        // Warnings got worse too, but they don't fail anything so they're only output
+        let element = if is_failure(report) {
+            format!(
+                "<failure message=\"{}\" type=\"worsened\">{}</failure>",
+                xml_escape(&summary),
+                xml_escape(&problems)
+            )
+        } else {
+            format!(
+                "<system-out>{}\n{}</system-out>",
+                xml_escape(&summary),
+                xml_escape(&problems)
+            )
+        };
+        let _ = write!(xml, ">\n      {element}\n    </testcase>\n");
+    } else {
+        xml.push_str("/>\n");
+    }
+}
+
+fn is_failure(report: &RuleReport) -> bool {
+    report.status == RuleStatus::Worsened && report.severity == Severity::Error
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{
+        reporters::{
+            RunInfo,
+            test::{TEST_RULE, sample_report},
+        },
+        rules::rule::Severity,
+    };
+
+    fn run() -> RunInfo<'static> {
+        RunInfo {
+            command: "check",
+            duration: Duration::from_millis(250),
+        }
+    }
+
+    #[test]
+    fn worse_rule_fails_with_new_problems() {
+        let xml = super::render(&sample_report(), &run()).unwrap();
+
+        assert!(xml.contains(
+            "<testsuite name=\"ratchet check\" tests=\"1\" failures=\"1\" skipped=\"0\" time=\"0.250\">"
+        ));
+        assert!(xml.contains(&format!(
+            "<testcase name=\"{TEST_RULE}\" classname=\"ratchet\">"
+        )));
+        assert!(xml.contains(
+            "<failure message=\"Rule got worse (1 new issues out of 3 total)\" type=\"worsened\">src/lib.rs:7:5 (widget)\nsrc/lib.rs:9:5 (widget)\n</failure>"
+        ));
+    }
+
+    #[test]
+    fn warnings_and_off_rules_dont_fail() {
+        let mut report = sample_report();
+        report.rules.get_mut(TEST_RULE).unwrap().severity = Severity::Warn;
+        let xml = super::render(&report, &run()).unwrap();
+        assert!(xml.contains("failures=\"0\""));
+        assert!(xml.contains("<system-out>Rule got worse"));
+
+        report.rules.get_mut(TEST_RULE).unwrap().severity = Severity::Off;
+        let xml = super::render(&report, &run()).unwrap();
+        assert!(xml.contains("skipped=\"1\""));
+        assert!(xml.contains("<skipped message=\"Rule is off\"/>"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{fs, path::Path, time::Duration};
+
+use crate::{
+    error::{RatchetError, Result},
+    report::CompareReport,
+};
+
+pub mod checkstyle;
+pub mod github;
+pub mod gitlab;
+pub mod html;
+pub mod json;
+pub mod junit;
+pub mod sarif;
+
+/// How the results of turning, checking or forcing are printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Emoji summary as the command runs
+    #[default]
+    Human,
+    /// A single JSON document with per rule totals, deltas and problems
+    Json,
+    /// SARIF 2.1.0 for code scanning tools
+    Sarif,
+    /// JUnit XML for CI test dashboards, each rule is a testcase
+    Junit,
+    /// GitHub Actions annotations for new problems, plus a job summary when `GITHUB_STEP_SUMMARY` is set
+    Github,
+    /// GitLab Code Quality JSON for the merge request widget
+    Gitlab,
+    /// Checkstyle XML for tools like Jenkins warnings plugins and Sonar importers
+    Checkstyle,
+}
+
+/// Details about the run that reports include alongside the results
+#[derive(Debug, Clone, Copy)]
+pub struct RunInfo<'a> {
+    /// The command that produced the report, like `check`
+    pub command: &'a str,
+    pub duration: Duration,
+}
+
+impl OutputFormat {
+    /// True if the format is meant for tools, so human output shouldn't mix in with it on stdout
+    pub fn is_machine_readable(self) -> bool {
+        self != OutputFormat::Human
+    }
+
+    /// Renders the report, human output is printed as the command runs so there's nothing to render
+    pub fn render(self, report: &CompareReport, run: &RunInfo) -> Result<Option<String>> {
+        match self {
+            OutputFormat::Human => Ok(None),
+            OutputFormat::Json => json::render(report, run).map(Some),
+            OutputFormat::Sarif => sarif::render(report, run).map(Some),
+            OutputFormat::Junit => junit::render(report, run).map(Some),
+            OutputFormat::Github => {
+                github::write_step_summary(report, run)?;
+                Ok(Some(github::render(report)))
+            }
+            OutputFormat::Gitlab => gitlab::render(report).map(Some),
+            OutputFormat::Checkstyle => checkstyle::render(report).map(Some),
+        }
+    }
+
+    /// Renders the report to a file, so it can be saved as a CI artifact alongside any other output
+    pub fn write(self, report: &CompareReport, run: &RunInfo, path: &Path) -> Result<()> {
+        match self.render(report, run)? {
+            Some(rendered) => {
+                fs::write(path, rendered).map_err(|error| RatchetError::io(path, error))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Escapes text for use in XML attributes and elements
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        ratchet_file::{FileEntry, Position, Problem},
+        report::CompareReport,
+    };
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            super::xml_escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+    }
+
+    pub(crate) const TEST_RULE: &str = "No more widgets";
+
+    fn problem(line: usize, message: &str, fingerprint: &str) -> Problem {
+        Problem {
+            start: Position::new(line, 5),
+            end: Position::new(line, 11),
+            message: message.into(),
+            message_hash: "hash".into(),
+            fingerprint: fingerprint.into(),
+        }
+    }
+
+    /// A report where src/lib.rs got worse, two problems were added, one was fixed and one stayed the same
+    pub(crate) fn sample_report() -> CompareReport {
+        let previous = BTreeMap::from([(
+            TEST_RULE.to_string(),
+            vec![FileEntry {
+                path: "src/lib.rs".into(),
+                hash: 1,
+                problems: vec![problem(2, "widget", "kept"), problem(4, "widget", "fixed")],
+            }],
+        )]);
+        let new = BTreeMap::from([(
+            TEST_RULE.to_string(),
+            vec![FileEntry {
+                path: "src/lib.rs".into(),
+                hash: 2,
+                problems: vec![
+                    problem(2, "widget", "kept"),
+                    problem(7, "widget", "added"),
+                    problem(9, "widget", "also added"),
+                ],
+            }],
+        )]);
+
+        CompareReport::new(&previous, &new)
+    }
+}
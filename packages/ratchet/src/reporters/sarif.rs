@@ -0,0 +1,179 @@
+//! SARIF 2.1.0, for code scanning tools. Each rule is a `reportingDescriptor` and each problem a result,
+//! problems that weren't in the previous ratchet file have a `baselineState` of `new`, the rest `unchanged`.
+
+use serde_json::{Value, json};
+
+use crate::{
+    error::{RatchetError, Result},
+    ratchet_file::{FileName, Problem, RuleName},
+    report::{CompareReport, RuleReport},
+    reporters::RunInfo,
+    rules::rule::Severity,
+};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+/// Paths in the ratchet file are relative to the project root, which SARIF consumers call the source root
+const SOURCE_ROOT: &str = "%SRCROOT%";
+
+/// Renders the report as a SARIF log with a single run
+pub fn render(report: &CompareReport, run: &RunInfo) -> Result<String> {
+    let rules: Vec<Value> = report
+        .rules
+        .iter()
+        .map(|(name, rule_report)| reporting_descriptor(name, rule_report))
+        .collect();
+
+    let results: Vec<Value> = report
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, rule_report))| rule_report.severity != Severity::Off)
+        .flat_map(|(index, (name, rule_report))| {
+            rule_report
+                .problems
+                .iter()
+                .map(move |(path, problem)| result(index, name, rule_report, path, problem))
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ratchet",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/lanesawyer/ratchet",
+                    "rules": rules,
+                },
+            },
+            "invocations": [{
+                "commandLine": format!("ratchet {}", run.command),
+                "executionSuccessful": true,
+                "properties": { "durationMs": run.duration.as_millis() },
+            }],
+            "results": results,
+        }],
+    });
+
+    serde_json::to_string_pretty(&log).map_err(RatchetError::SerializeReport)
+}
+
+fn reporting_descriptor(name: &RuleName, report: &RuleReport) -> Value {
+    json!({
+        "id": name,
+        "name": name,
+        "shortDescription": { "text": name },
+        "defaultConfiguration": {
+            "enabled": report.severity != Severity::Off,
+            "level": level(report.severity),
+        },
+        "properties": {
+            "previousCount": report.previous_count,
+            "newCount": report.new_count,
+        },
+    })
+}
+
+fn result(
+    rule_index: usize,
+    rule: &RuleName,
+    report: &RuleReport,
+    path: &FileName,
+    problem: &Problem,
+) -> Value {
+    let baseline_state = if report.is_new(path, problem) {
+        "new"
+    } else {
+        "unchanged"
+    };
+
+    json!({
+        "ruleId": rule,
+        "ruleIndex": rule_index,
+        "level": level(report.severity),
+        "message": { "text": message(rule, problem) },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": path, "uriBaseId": SOURCE_ROOT },
+                "region": {
+                    "startLine": problem.start.line,
+                    "startColumn": problem.start.column,
+                    "endLine": problem.end.line,
+                    "endColumn": problem.end.column,
+                },
+            },
+        }],
+        "partialFingerprints": { "ratchet/v1": problem.fingerprint },
+        "baselineState": baseline_state,
+    })
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warn => "warning",
+        Severity::Off => "none",
+    }
+}
+
+/// Regex rules use the matched text as the message, which on its own doesn't say much
+fn message(rule: &RuleName, problem: &Problem) -> String {
+    format!("{rule}: {}", problem.message)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{Value, json};
+
+    use crate::reporters::{
+        RunInfo,
+        test::{TEST_RULE, sample_report},
+    };
+
+    #[test]
+    fn marks_new_problems_with_baseline_state() {
+        let run = RunInfo {
+            command: "check",
+            duration: Duration::from_millis(20),
+        };
+
+        let rendered = super::render(&sample_report(), &run).unwrap();
+        let sarif: Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(sarif["version"], "2.1.0");
+        let sarif_run = &sarif["runs"][0];
+        assert_eq!(sarif_run["tool"]["driver"]["rules"][0]["id"], TEST_RULE);
+
+        let results = sarif_run["results"].as_array().unwrap();
+        let states: Vec<(&Value, &Value)> = results
+            .iter()
+            .map(|result| {
+                let line = &result["locations"][0]["physicalLocation"]["region"]["startLine"];
+                (line, &result["baselineState"])
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (&json!(2), &json!("unchanged")),
+                (&json!(7), &json!("new")),
+                (&json!(9), &json!("new")),
+            ]
+        );
+
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "src/lib.rs", "uriBaseId": "%SRCROOT%" },
+                "region": { "startLine": 7, "startColumn": 5, "endLine": 7, "endColumn": 11 },
+            })
+        );
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(results[1]["partialFingerprints"]["ratchet/v1"], "added");
+    }
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashSet, path::Path, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ratchet_file::{Position, Problem},
+    rules::{
+        external::{
+            ExternalIssue, IssueMap, ProjectIssues, absolute_root, check_source, is_wanted,
+            read_results, relative_path,
+        },
+        path_matcher::PatternSyntax,
+        rule::{Checker, RuleConfigError, Severity},
+    },
+};
+
+/// Clippy builds the project first, so it gets longer than most commands
+const DEFAULT_CLIPPY_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClippyRule {
+    /// Saved output of `cargo clippy --message-format=json`, relative to the working directory
+    pub file: Option<PathBuf>,
+    /// Command to run instead of reading a file, defaults to `cargo clippy --message-format=json`
+    pub command: Option<Vec<String>>,
+    /// Only keep these lints, like `clippy::unwrap_used` or `unused_variables`
+    pub lints: Option<Vec<String>>,
+    /// Only keep diagnostics at these levels, like `warning` or `error`
+    pub levels: Option<Vec<String>>,
+    /// Seconds to wait for the command before failing the rule
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Directory the command runs in, or the file is read from, relative to the root.
+    /// Cargo prints paths relative to the workspace, so this should be the workspace root
+    pub working_dir: Option<PathBuf>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub pattern_syntax: PatternSyntax,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_CLIPPY_TIMEOUT_SECS
+}
+
+fn default_command() -> Vec<String> {
+    ["cargo", "clippy", "--message-format=json"]
+        .map(String::from)
+        .to_vec()
+}
+
+impl ClippyRule {
+    pub fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+        check_source(self.file.as_deref(), self.command.as_deref())?;
+
+        Ok(Box::new(ClippyChecker {
+            file: self.file.clone(),
+            command: self.command.clone(),
+            lints: self.lints.clone(),
+            levels: self.levels.clone(),
+            timeout: Duration::from_secs(self.timeout),
+            working_dir: self.working_dir.clone().unwrap_or_default(),
+            issues: ProjectIssues::default(),
+        }))
+    }
+}
+
+/// A line of cargo's JSON output, only compiler messages matter
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    message: String,
+    code: Option<DiagnosticCode>,
+    level: String,
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticSpan {
+    file_name: String,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+}
+
+/// Turns the diagnostics from a cargo build into problems on their primary spans
+pub struct ClippyChecker {
+    file: Option<PathBuf>,
+    command: Option<Vec<String>>,
+    lints: Option<Vec<String>>,
+    levels: Option<Vec<String>>,
+    timeout: Duration,
+    working_dir: PathBuf,
+    issues: ProjectIssues,
+}
+
+impl ClippyChecker {
+    fn keep(&self, diagnostic: &Diagnostic) -> bool {
+        let lint = diagnostic.code.as_ref().map(|code| code.code.as_str());
+
+        is_wanted(self.lints.as_deref(), lint)
+            && is_wanted(self.levels.as_deref(), Some(&diagnostic.level))
+    }
+
+    /// Collects every kept diagnostic by file, relative to the root
+    fn parse(&self, output: &str, root: &Path, working_dir: &Path) -> IssueMap {
+        let mut issues = IssueMap::new();
+        // Cargo reports the same diagnostic once per target that includes the file, like lib and tests
+        let mut seen = HashSet::new();
+
+        let diagnostics = output
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+            .filter(|message| message.reason == "compiler-message")
+            .filter_map(|message| message.message)
+            .filter(|diagnostic| self.keep(diagnostic));
+
+        for diagnostic in diagnostics {
+            // Summaries like "N warnings emitted" don't point anywhere
+            let Some(span) = diagnostic.spans.iter().find(|span| span.is_primary) else {
+                continue;
+            };
+            // Diagnostics from macros can point into dependencies, which aren't ours to fix
+            let Some(path) = relative_path(root, working_dir, &span.file_name) else {
+                continue;
+            };
+
+            let issue = ExternalIssue {
+                start: Position::new(span.line_start, span.column_start),
+                end: Some(Position::new(span.line_end, span.column_end)),
+                message: diagnostic.message.clone(),
+                code: diagnostic.code.as_ref().map(|code| code.code.clone()),
+            };
+            if seen.insert((path.clone(), issue.clone())) {
+                issues.entry(path).or_default().push(issue);
+            }
+        }
+
+        issues
+    }
+}
+
+/// Cargo exits with a failure when the code has errors, but then it still reports on the build.
+/// Without any of that, cargo or clippy couldn't run at all
+fn has_cargo_messages(output: &str) -> bool {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .any(|message| {
+            matches!(
+                message.reason.as_str(),
+                "compiler-message" | "build-finished"
+            )
+        })
+}
+
+impl Checker for ClippyChecker {
+    fn prepare(&self, root: &Path) -> Result<(), String> {
+        let root = absolute_root(root)?;
+        let working_dir = root.join(&self.working_dir);
+        let command = self.command.clone().unwrap_or_else(default_command);
+
+        let output = read_results(
+            self.file.as_deref(),
+            &command,
+            &working_dir,
+            self.timeout,
+            has_cargo_messages,
+        )?;
+        self.issues.set(self.parse(&output, &root, &working_dir));
+
+        Ok(())
+    }
+
+    fn check(&self, path: &str, content: &str) -> Result<Vec<Problem>, String> {
+        Ok(self.issues.check(path, content))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClippyRule;
+    use crate::{
+        ratchet_file::{Position, Problem},
+        rules::external::test::{check_fixture, fixture_root, from_settings, quoted},
+    };
+
+    /// A rule reading the recorded output, with any other settings added on
+    fn rule(settings: &str) -> ClippyRule {
+        from_settings(&format!("file = \"clippy.json\"\n{settings}"))
+    }
+
+    fn check(rule: ClippyRule) -> Vec<Problem> {
+        check_fixture(
+            rule.compile().unwrap(),
+            &fixture_root("clippy"),
+            "src/main.rs",
+        )
+    }
+
+    #[test]
+    fn reads_diagnostics_from_recorded_output() {
+        let problems = check(rule(""));
+
+        let found: Vec<(Position, &str)> = problems
+            .iter()
+            .map(|problem| (problem.start, problem.message_hash.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Position::new(4, 5), "clippy::needless_return"),
+                (Position::new(8, 9), "unused_variables"),
+                (Position::new(10, 27), "clippy::unnecessary_literal_unwrap"),
+                (Position::new(10, 27), "clippy::unwrap_used"),
+            ]
+        );
+
+        // The primary span covers just the offending code
+        assert_eq!(problems[1].end, Position::new(8, 15));
+        assert_eq!(problems[1].message, "unused variable: `unused`");
+        assert_ne!(problems[2].fingerprint, problems[3].fingerprint);
+    }
+
+    #[test]
+    fn filters_by_lint_and_level() {
+        let problems = check(rule("lints = [\"clippy::unwrap_used\"]"));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message_hash, "clippy::unwrap_used");
+
+        assert_eq!(check(rule("levels = [\"warning\"]")).len(), 4);
+        assert!(check(rule("levels = [\"error\"]")).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cargo_failing_without_messages_fails_the_rule() {
+        let missing: ClippyRule = from_settings(&format!(
+            "command = [\"sh\", \"-c\", {}]\n",
+            quoted("echo 'error: no such command: `clippy`' >&2; exit 101")
+        ));
+
+        let error = missing
+            .compile()
+            .unwrap()
+            .prepare(&fixture_root("clippy"))
+            .err();
+        assert!(error.unwrap().contains("no such command"));
+    }
+
+    #[test]
+    fn file_and_command_cant_both_be_set() {
+        let both = rule("command = [\"cargo\"]");
+
+        let error = both.compile().err().unwrap();
+        assert!(error.to_string().contains("not both"));
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+
+use crate::{
+    ratchet_file::{Position, Problem},
+    rules::{
+        external::{
+            DEFAULT_TIMEOUT_SECS, ExternalIssue, IssueMap, ProjectIssues, absolute_root,
+            relative_path, run_command, to_problems,
+        },
+        path_matcher::PatternSyntax,
+        rule::{Checker, RuleConfigError, Severity},
+    },
+};
+
+/// Replaced with the path of the file being checked when running once per file
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandRule {
+    /// The program to run followed by its arguments
+    #[serde(deserialize_with = "non_empty_command")]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub mode: CommandMode,
+    /// Regex matched against each line of output, with a `line` named group and
+    /// optional `file`, `column` and `message` groups. `file` is required when running once
+    pub pattern: String,
+    /// Seconds to wait for the command before failing the rule
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Directory to run the command in, relative to the root. Defaults to the root
+    pub working_dir: Option<PathBuf>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub pattern_syntax: PatternSyntax,
+}
+
+/// How often the command runs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandMode {
+    /// A single run for the whole project, the output says which file each problem is in
+    #[default]
+    Once,
+    /// A run for every file the rule applies to, with the file's path passed in place of `{file}`,
+    /// or as the last argument if there's no placeholder
+    PerFile,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn non_empty_command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let command = Vec::<String>::deserialize(deserializer)?;
+    if command.is_empty() {
+        return Err(D::Error::custom(
+            "command needs at least the program to run",
+        ));
+    }
+    Ok(command)
+}
+
+impl CommandRule {
+    pub fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+        let pattern = Regex::new(&self.pattern)?;
+
+        let has_group = |name| pattern.capture_names().any(|group| group == Some(name));
+        if !has_group("line") {
+            return Err(RuleConfigError::invalid(
+                "pattern",
+                "pattern needs a `line` named group",
+            ));
+        }
+        if self.mode == CommandMode::Once && !has_group("file") {
+            return Err(RuleConfigError::invalid(
+                "pattern",
+                "pattern needs a `file` named group when the command runs once",
+            ));
+        }
+
+        Ok(Box::new(CommandChecker {
+            command: self.command.clone(),
+            mode: self.mode,
+            pattern,
+            timeout: Duration::from_secs(self.timeout),
+            working_dir: self.working_dir.clone().unwrap_or_default(),
+            root: OnceLock::new(),
+            issues: ProjectIssues::default(),
+        }))
+    }
+}
+
+/// Runs a command and turns the lines of its output matching a pattern into problems
+pub struct CommandChecker {
+    command: Vec<String>,
+    mode: CommandMode,
+    pattern: Regex,
+    timeout: Duration,
+    working_dir: PathBuf,
+    /// Absolute root of the scan, set when the checker is prepared
+    root: OnceLock<PathBuf>,
+    issues: ProjectIssues,
+}
+
+impl CommandChecker {
+    /// Runs the command and matches each line of stdout and stderr, giving back the file it names
+    /// along with the issue. A failing command that printed nothing matching has most likely crashed
+    /// or been misconfigured, which shouldn't look like every problem being fixed
+    fn run(
+        &self,
+        command: &[String],
+        working_dir: &Path,
+    ) -> Result<Vec<(Option<String>, ExternalIssue)>, String> {
+        let output = run_command(command, working_dir, self.timeout)?;
+        let matches: Vec<(Option<String>, ExternalIssue)> = output
+            .stdout
+            .lines()
+            .chain(output.stderr.lines())
+            .filter_map(|line| self.parse_line(line))
+            .collect();
+
+        if matches.is_empty() && !output.status.success() {
+            return Err(output.failure(&command[0]));
+        }
+        Ok(matches)
+    }
+
+    /// Matches a line of output, giving back the file it names along with the issue
+    fn parse_line(&self, line: &str) -> Option<(Option<String>, ExternalIssue)> {
+        let captures = self.pattern.captures(line)?;
+        let number = |captures: &Captures, name| -> Option<usize> {
+            captures.name(name)?.as_str().trim().parse().ok()
+        };
+
+        let line_number = number(&captures, "line")?;
+        let column = number(&captures, "column").unwrap_or(1);
+        let message = captures
+            .name("message")
+            .map_or(line, |message| message.as_str())
+            .trim()
+            .to_string();
+        let file = captures
+            .name("file")
+            .map(|file| file.as_str().trim().to_string());
+
+        let issue = ExternalIssue {
+            start: Position::new(line_number.max(1), column.max(1)),
+            end: None,
+            message,
+            code: None,
+        };
+        Some((file, issue))
+    }
+}
+
+impl Checker for CommandChecker {
+    fn prepare(&self, root: &Path) -> Result<(), String> {
+        let root = absolute_root(root)?;
+        let working_dir = root.join(&self.working_dir);
+
+        if self.mode == CommandMode::Once {
+            let mut issues = IssueMap::new();
+            for (file, issue) in self.run(&self.command, &working_dir)? {
+                if let Some(path) = file.and_then(|file| relative_path(&root, &working_dir, &file))
+                {
+                    issues.entry(path).or_default().push(issue);
+                }
+            }
+            self.issues.set(issues);
+        }
+
+        let _ = self.root.set(root);
+        Ok(())
+    }
+
+    fn check(&self, path: &str, content: &str) -> Result<Vec<Problem>, String> {
+        if self.mode == CommandMode::Once {
+            return Ok(self.issues.check(path, content));
+        }
+
+        let root = self
+            .root
+            .get()
+            .ok_or_else(|| "The command rule was checked before it was prepared".to_string())?;
+        let file = root.join(path).to_string_lossy().into_owned();
+
+        let mut command: Vec<String> = self
+            .command
+            .iter()
+            .map(|arg| arg.replace(FILE_PLACEHOLDER, &file))
+            .collect();
+        if !self
+            .command
+            .iter()
+            .any(|arg| arg.contains(FILE_PLACEHOLDER))
+        {
+            command.push(file);
+        }
+
+        let issues: Vec<ExternalIssue> = self
+            .run(&command, &root.join(&self.working_dir))?
+            .into_iter()
+            .map(|(_, issue)| issue)
+            .collect();
+
+        Ok(to_problems(content, &issues))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::{env, fs, path::PathBuf};
+
+    use super::CommandRule;
+    use crate::{
+        ratchet_file::Position,
+        rules::{
+            external::test::{from_settings, quoted},
+            rule::RuleConfigError,
+        },
+    };
+
+    const PATTERN: &str = r"^(?P<file>[^:]+):(?P<line>\d+):(?P<column>\d+): (?P<message>.*)$";
+
+    fn rule(command: &str, mode: &str, pattern: &str) -> CommandRule {
+        from_settings(&format!(
+            "command = [\"sh\", \"-c\", {}]\nmode = \"{mode}\"\npattern = {}\ntimeout = 5\n",
+            quoted(command),
+            quoted(pattern)
+        ))
+    }
+
+    fn project(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("ratchet-command-{name}-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        root
+    }
+
+    #[test]
+    fn runs_once_and_hands_out_problems_by_file() {
+        let root = project("once");
+        let output = "src/a.py:2:5: first\\n./src/b.py:1:1: second\\nnoise\\nsrc/a.py:1:1: third";
+        let checker = rule(&format!("printf '{output}'"), "once", PATTERN)
+            .compile()
+            .unwrap();
+
+        checker.prepare(&root).unwrap();
+        let a = checker.check("src/a.py", "x = 1\ny = 2\n").unwrap();
+        let b = checker.check("src/b.py", "z = 3\n").unwrap();
+        let c = checker.check("src/c.py", "w = 4\n").unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[0].start, Position::new(1, 1));
+        assert_eq!(a[0].message, "third");
+        assert_eq!(a[1].start, Position::new(2, 5));
+        assert_eq!(a[1].message, "first");
+        assert_eq!(b.len(), 1);
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn runs_per_file_with_the_file_path() {
+        let root = project("per-file");
+        fs::write(root.join("src/a.sh"), "echo hi\n").unwrap();
+        // Prints the file it was given so the test can see the placeholder was filled in
+        let command = r#"echo "1:6: checked $(basename {file})""#;
+        let checker = rule(
+            command,
+            "per_file",
+            r"^(?P<line>\d+):(?P<column>\d+): (?P<message>.*)$",
+        )
+        .compile()
+        .unwrap();
+
+        checker.prepare(&root).unwrap();
+        let problems = checker.check("src/a.sh", "echo hi\n").unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message, "checked a.sh");
+        assert_eq!(problems[0].start, Position::new(1, 6));
+    }
+
+    #[test]
+    fn patterns_need_line_and_file_groups() {
+        let no_line = rule("true", "per_file", r"^(?P<file>.*)$").compile();
+        assert!(matches!(
+            &no_line,
+            Err(RuleConfigError::Invalid {
+                field: "pattern",
+                ..
+            })
+        ));
+        assert!(no_line.err().unwrap().to_string().contains("`line`"));
+
+        let no_file = rule("true", "once", r"^(?P<line>\d+)$").compile();
+        assert!(no_file.err().unwrap().to_string().contains("`file`"));
+    }
+
+    #[test]
+    fn failing_commands_only_fail_the_rule_without_results() {
+        let root = project("failing");
+        let crashed = rule("echo 'config not found' >&2; exit 2", "once", PATTERN)
+            .compile()
+            .unwrap();
+        let error = crashed.prepare(&root).err().unwrap();
+
+        // Linters usually exit with a failure whenever they find something
+        let found = rule("echo 'src/a.py:1:1: first'; exit 1", "once", PATTERN)
+            .compile()
+            .unwrap();
+        found.prepare(&root).unwrap();
+        let problems = found.check("src/a.py", "x = 1\n").unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(error.contains("exit status: 2"));
+        assert!(error.contains("config not found"));
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn slow_commands_fail_the_rule() {
+        let root = project("timeout");
+        let mut slow = rule("sleep 5", "once", PATTERN);
+        slow.timeout = 0;
+        let checker = slow.compile().unwrap();
+
+        let error = checker.prepare(&root).err().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert!(error.contains("didn't finish within 0s"));
+    }
+}
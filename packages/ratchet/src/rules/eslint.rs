@@ -0,0 +1,243 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ratchet_file::{Position, Problem},
+    rules::{
+        external::{
+            ExternalIssue, IssueMap, ProjectIssues, absolute_root, check_source, is_wanted,
+            read_results, relative_path,
+        },
+        path_matcher::PatternSyntax,
+        rule::{Checker, RuleConfigError, Severity},
+    },
+};
+
+/// ESLint with type aware rules can take a while on a large project
+const DEFAULT_ESLINT_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EslintRule {
+    /// Saved output of `eslint --format json`, relative to the working directory
+    pub file: Option<PathBuf>,
+    /// Command to run instead of reading a file, defaults to `npx eslint --format json .`
+    pub command: Option<Vec<String>>,
+    /// Only keep messages from these ESLint rules, like `no-console` or `@typescript-eslint/no-explicit-any`
+    pub rule_ids: Option<Vec<String>>,
+    /// Seconds to wait for the command before failing the rule
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Directory the command runs in, or the file is read from, relative to the root. Defaults to the root
+    pub working_dir: Option<PathBuf>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub pattern_syntax: PatternSyntax,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_ESLINT_TIMEOUT_SECS
+}
+
+fn default_command() -> Vec<String> {
+    ["npx", "eslint", "--format", "json", "."]
+        .map(String::from)
+        .to_vec()
+}
+
+impl EslintRule {
+    pub fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+        Ok(Box::new(self.checker()?))
+    }
+
+    fn checker(&self) -> Result<EslintChecker, RuleConfigError> {
+        check_source(self.file.as_deref(), self.command.as_deref())?;
+
+        Ok(EslintChecker {
+            file: self.file.clone(),
+            command: self.command.clone(),
+            rule_ids: self.rule_ids.clone(),
+            timeout: Duration::from_secs(self.timeout),
+            working_dir: self.working_dir.clone().unwrap_or_default(),
+            issues: ProjectIssues::default(),
+        })
+    }
+}
+
+/// The results for a single file in ESLint's JSON output
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileResult {
+    file_path: String,
+    messages: Vec<Message>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    /// Missing for fatal errors, like a file that doesn't parse
+    rule_id: Option<String>,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+}
+
+/// Turns the messages from an ESLint run into problems
+pub struct EslintChecker {
+    file: Option<PathBuf>,
+    command: Option<Vec<String>>,
+    rule_ids: Option<Vec<String>>,
+    timeout: Duration,
+    working_dir: PathBuf,
+    issues: ProjectIssues,
+}
+
+impl EslintChecker {
+    fn keep(&self, message: &Message) -> bool {
+        is_wanted(self.rule_ids.as_deref(), message.rule_id.as_deref())
+    }
+
+    /// Collects every kept message by file, relative to the root
+    fn parse(&self, output: &str, root: &Path, working_dir: &Path) -> Result<IssueMap, String> {
+        let results: Vec<FileResult> = serde_json::from_str(output)
+            .map_err(|e| format!("Failed to parse ESLint's JSON output: {e}"))?;
+
+        let mut issues = IssueMap::new();
+        for result in results {
+            let Some(path) = relative_path(root, working_dir, &result.file_path) else {
+                continue;
+            };
+
+            for message in result.messages.into_iter().filter(|m| self.keep(m)) {
+                let start = Position::new(message.line.unwrap_or(1), message.column.unwrap_or(1));
+                let end = message
+                    .end_column
+                    .map(|column| Position::new(message.end_line.unwrap_or(start.line), column));
+
+                issues.entry(path.clone()).or_default().push(ExternalIssue {
+                    start,
+                    end,
+                    message: message.message,
+                    code: message.rule_id,
+                });
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+impl Checker for EslintChecker {
+    fn prepare(&self, root: &Path) -> Result<(), String> {
+        let root = absolute_root(root)?;
+        let working_dir = root.join(&self.working_dir);
+        let command = self.command.clone().unwrap_or_else(default_command);
+
+        // ESLint exits with a failure when it finds problems, but only prints its results when it could run
+        let output = read_results(
+            self.file.as_deref(),
+            &command,
+            &working_dir,
+            self.timeout,
+            |stdout| stdout.trim_start().starts_with('['),
+        )?;
+        self.issues.set(self.parse(&output, &root, &working_dir)?);
+
+        Ok(())
+    }
+
+    fn check(&self, path: &str, content: &str) -> Result<Vec<Problem>, String> {
+        Ok(self.issues.check(path, content))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::EslintRule;
+    use crate::{
+        ratchet_file::Position,
+        rules::external::{
+            IssueMap,
+            test::{fixture_root, from_settings},
+            to_problems,
+        },
+    };
+
+    /// ESLint prints absolute paths, the fixture was recorded in `/project`
+    const RECORDED_ROOT: &str = "/project";
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(fixture_root("eslint").join(name)).unwrap()
+    }
+
+    fn parse(settings: &str) -> IssueMap {
+        let rule: EslintRule = from_settings(settings);
+        let checker = rule.checker().unwrap();
+        let root = Path::new(RECORDED_ROOT);
+
+        checker.parse(&fixture("eslint.json"), root, root).unwrap()
+    }
+
+    #[test]
+    fn reads_messages_from_recorded_output() {
+        let issues = parse("");
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues["src/broken.ts"][0].code, None);
+
+        let problems = to_problems(&fixture("src/app.ts"), &issues["src/app.ts"]);
+        let found: Vec<(Position, Position, &str)> = problems
+            .iter()
+            .map(|problem| (problem.start, problem.end, problem.message_hash.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Position::new(1, 7),
+                    Position::new(1, 13),
+                    "@typescript-eslint/no-unused-vars"
+                ),
+                (
+                    Position::new(3, 29),
+                    Position::new(3, 32),
+                    "@typescript-eslint/no-explicit-any"
+                ),
+                (Position::new(4, 3), Position::new(4, 14), "no-console"),
+                (Position::new(5, 10), Position::new(5, 14), "no-eval"),
+            ]
+        );
+        assert_eq!(problems[3].message, "eval can be harmful.");
+    }
+
+    #[test]
+    fn keeps_only_selected_rule_ids() {
+        let issues = parse("rule_ids = [\"no-console\", \"no-eval\"]");
+
+        assert_eq!(issues.len(), 1);
+        let codes: Vec<Option<&str>> = issues["src/app.ts"]
+            .iter()
+            .map(|issue| issue.code.as_deref())
+            .collect();
+        assert_eq!(codes, vec![Some("no-console"), Some("no-eval")]);
+    }
+
+    #[test]
+    fn file_and_command_cant_both_be_set() {
+        let both: EslintRule =
+            from_settings("file = \"eslint.json\"\ncommand = [\"npx\", \"eslint\"]\n");
+
+        let error = both.compile().err().unwrap();
+        assert!(error.to_string().contains("not both"));
+    }
+}
@@ -0,0 +1,408 @@
+//! Shared plumbing for rules that get their problems from another tool instead of reading files themselves
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use wait_timeout::ChildExt;
+
+use crate::{
+    ratchet_file::{Position, Problem},
+    rules::rule::RuleConfigError,
+    utils::{LineIndex, fingerprint, to_normalized_path},
+};
+
+/// A problem as another tool reported it, before it's tied to the contents of the file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternalIssue {
+    pub start: Position,
+    /// Where the tool says the problem ends, the rest of the line if it doesn't say
+    pub end: Option<Position>,
+    pub message: String,
+    /// What kind of problem it is, like a lint name. The message is used when there isn't one
+    pub code: Option<String>,
+}
+
+/// Issues for a whole project, keyed by path relative to the root
+pub type IssueMap = HashMap<String, Vec<ExternalIssue>>;
+
+/// How long a command gets to run when a rule doesn't say
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// What a command printed and how it exited
+pub struct CommandOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Describes a run that failed without giving any results, with whatever it printed to stderr
+    pub fn failure(&self, program: &str) -> String {
+        match self.stderr.trim() {
+            "" => format!("`{program}` failed with {}", self.status),
+            stderr => format!("`{program}` failed with {}: {stderr}", self.status),
+        }
+    }
+}
+
+impl ExternalIssue {
+    pub fn to_problem(&self, content: &str, line_index: &LineIndex) -> Problem {
+        let start = line_index.offset(self.start);
+        let end = match self.end {
+            Some(end) => line_index.offset(end).max(start),
+            None => line_index.line_end(self.start.line).max(start),
+        };
+
+        let message_hash = match &self.code {
+            Some(code) => code.clone(),
+            None => seahash::hash(self.message.as_bytes()).to_string(),
+        };
+        // Tools often report several problems on the same line, so the kind of problem is part of it too
+        let fingerprint = format!("{}{}", fingerprint(content, start, end), message_hash);
+
+        Problem {
+            start: line_index.position(start),
+            end: line_index.position(end),
+            message: self.message.clone(),
+            message_hash,
+            fingerprint: seahash::hash(fingerprint.as_bytes()).to_string(),
+        }
+    }
+}
+
+/// Turns issues for a single file into problems, sorted by where they start
+pub fn to_problems(content: &str, issues: &[ExternalIssue]) -> Vec<Problem> {
+    let line_index = LineIndex::new(content);
+    let mut problems: Vec<Problem> = issues
+        .iter()
+        .map(|issue| issue.to_problem(content, &line_index))
+        .collect();
+    problems.sort_by_key(|problem| (problem.start, problem.end));
+    problems
+}
+
+/// True when there's no list to filter by, or the value is one of the wanted ones.
+/// A value that's missing, like a problem without a rule id, is only kept when there's no list
+pub fn is_wanted(wanted: Option<&[String]>, value: Option<&str>) -> bool {
+    wanted.is_none_or(|wanted| value.is_some_and(|value| wanted.iter().any(|want| want == value)))
+}
+
+/// Issues gathered once for the whole project, then handed out as each file is checked
+#[derive(Default)]
+pub struct ProjectIssues {
+    issues: OnceLock<IssueMap>,
+}
+
+impl ProjectIssues {
+    pub fn set(&self, issues: IssueMap) {
+        // Prepare only runs once per run, if it somehow runs again the first results win
+        let _ = self.issues.set(issues);
+    }
+
+    pub fn check(&self, path: &str, content: &str) -> Vec<Problem> {
+        self.issues
+            .get()
+            .and_then(|issues| issues.get(path))
+            .map(|issues| to_problems(content, issues))
+            .unwrap_or_default()
+    }
+}
+
+/// Gets a tool's results from the file it saved them to, or by running it when there's no file.
+/// Paths in the results are relative to the working directory either way.
+/// A run that fails is only an error when `has_results` doesn't recognize anything in its stdout,
+/// otherwise a tool that couldn't run would look like every problem was fixed
+pub fn read_results(
+    file: Option<&Path>,
+    command: &[String],
+    working_dir: &Path,
+    timeout: Duration,
+    has_results: impl Fn(&str) -> bool,
+) -> Result<String, String> {
+    match file {
+        Some(file) => {
+            let path = working_dir.join(file);
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))
+        }
+        None => {
+            let output = run_command(command, working_dir, timeout)?;
+            if !output.status.success() && !has_results(&output.stdout) {
+                return Err(output.failure(&command[0]));
+            }
+            Ok(output.stdout)
+        }
+    }
+}
+
+/// A rule can read results from a file or a command, but not both.
+/// Checked when the rule compiles, so `ratchet validate` points at the setting
+pub fn check_source(
+    file: Option<&Path>,
+    command: Option<&[String]>,
+) -> Result<(), RuleConfigError> {
+    match (file, command) {
+        (Some(_), Some(_)) => Err(RuleConfigError::invalid(
+            "command",
+            "set either `file` or `command`, not both",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves the root to an absolute path, so paths tools print can be matched against it
+pub fn absolute_root(root: &Path) -> Result<PathBuf, String> {
+    root.canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {e}", root.display()))
+}
+
+/// Turns a path a tool printed into one relative to the root, like every path in the ratchet file.
+/// Relative paths are relative to the directory the tool ran in. Paths outside the root give `None`
+pub fn relative_path(root: &Path, working_dir: &Path, path: &str) -> Option<String> {
+    let path = Path::new(path);
+    let full_path = if path.is_absolute() {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        working_dir.join(path)
+    };
+
+    let full_path = normalize(&full_path);
+    let relative = full_path.strip_prefix(normalize(root)).ok()?;
+    Some(to_normalized_path(relative))
+}
+
+/// Drops `.` and resolves `..` without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Runs a program with its arguments, killing it if it runs longer than the timeout.
+/// On unix the command gets its own process group so anything it started is killed along with it.
+/// A failing exit status isn't an error here, linters exit with one whenever they find something.
+/// Callers check `status` once they know whether the output had any results
+pub fn run_command(
+    command: &[String],
+    working_dir: &Path,
+    timeout: Duration,
+) -> Result<CommandOutput, String> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| "No command to run".to_string())?;
+
+    let mut process = Command::new(program);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+    let mut child = process
+        .args(args)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            format!(
+                "Failed to run `{program}` in {}: {e}",
+                working_dir.display()
+            )
+        })?;
+
+    // Output is read on other threads so a chatty command can't fill the pipe and block forever
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let status = match child.wait_timeout(timeout) {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            kill_process_group(child.id());
+            let _ = child.kill();
+            let _ = child.wait();
+            // Elsewhere, children of the command can outlive it and keep the pipes open, so the readers are left behind
+            if cfg!(unix) {
+                let _ = stdout.join();
+                let _ = stderr.join();
+            }
+            return Err(format!(
+                "`{program}` didn't finish within {}s",
+                timeout.as_secs()
+            ));
+        }
+        Err(e) => return Err(format!("Failed to wait for `{program}`: {e}")),
+    };
+
+    Ok(CommandOutput {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Kills the group started for a command, which is everything it ran that didn't start its own group
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{pid}")])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: u32) {}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use serde::de::DeserializeOwned;
+
+    use super::{ExternalIssue, is_wanted, relative_path, run_command, to_problems};
+    use crate::{
+        ratchet_file::{Position, Problem},
+        rules::rule::Checker,
+    };
+
+    /// The project a rule's recorded tool output was saved in, under `fixtures`
+    pub fn fixture_root(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(name)
+    }
+
+    pub fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    /// Builds a rule from the settings it would have under its `[rules.<name>]` table
+    pub fn from_settings<T: DeserializeOwned>(settings: &str) -> T {
+        toml::from_str(settings).unwrap()
+    }
+
+    /// Quotes a value for a TOML settings snippet
+    pub fn quoted(value: &str) -> String {
+        toml::Value::String(value.into()).to_string()
+    }
+
+    /// Prepares a compiled rule against a fixture project, then checks one of its files
+    pub fn check_fixture(checker: Box<dyn Checker>, root: &Path, path: &str) -> Vec<Problem> {
+        let content = fs::read_to_string(root.join(path)).unwrap();
+
+        checker.prepare(root).unwrap();
+        checker.check(path, &content).unwrap()
+    }
+
+    fn issue(line: usize, column: usize, message: &str) -> ExternalIssue {
+        ExternalIssue {
+            start: Position::new(line, column),
+            end: None,
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    #[test]
+    fn issues_become_problems_covering_the_rest_of_the_line() {
+        let content = "fn main() {\n    let x = 1;\n}\n";
+        let problems = to_problems(content, &[issue(2, 9, "unused variable")]);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].start, Position::new(2, 9));
+        assert_eq!(problems[0].end, Position::new(2, 15));
+        assert_eq!(problems[0].message, "unused variable");
+    }
+
+    #[test]
+    fn fingerprints_depend_on_the_kind_of_problem() {
+        let content = "let x = 1;\n";
+        let problems = to_problems(content, &[issue(1, 1, "one"), issue(1, 1, "two")]);
+        assert_ne!(problems[0].fingerprint, problems[1].fingerprint);
+
+        // Moving the line doesn't change it
+        let moved = to_problems("\n\nlet x = 1;\n", &[issue(3, 1, "one")]);
+        assert_eq!(problems[0].fingerprint, moved[0].fingerprint);
+    }
+
+    #[test]
+    fn lists_only_filter_when_set() {
+        let wanted = strings(&["no-eval"]);
+
+        assert!(is_wanted(None, None));
+        assert!(is_wanted(Some(&wanted), Some("no-eval")));
+        assert!(!is_wanted(Some(&wanted), Some("no-console")));
+        assert!(!is_wanted(Some(&wanted), None));
+    }
+
+    #[test]
+    fn paths_are_made_relative_to_the_root() {
+        let root = Path::new("/project");
+        let working_dir = Path::new("/project/app");
+
+        assert_eq!(
+            relative_path(root, working_dir, "./src/main.py"),
+            Some("app/src/main.py".to_string())
+        );
+        assert_eq!(
+            relative_path(root, working_dir, "../lib/util.py"),
+            Some("lib/util.py".to_string())
+        );
+        assert_eq!(
+            relative_path(root, working_dir, "/project/lib/util.py"),
+            Some("lib/util.py".to_string())
+        );
+        assert_eq!(relative_path(root, working_dir, "../../elsewhere.py"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commands_are_killed_after_the_timeout() {
+        let command = ["sleep".to_string(), "5".to_string()];
+        let error = run_command(&command, Path::new("."), Duration::from_millis(100))
+            .err()
+            .unwrap();
+        assert!(error.contains("didn't finish"));
+
+        // The background sleep holds the pipes open, so this only returns once it's killed too
+        let command = strings(&["sh", "-c", "sleep 5 & sleep 5"]);
+        let started = std::time::Instant::now();
+        let error = run_command(&command, Path::new("."), Duration::from_millis(100))
+            .err()
+            .unwrap();
+        assert!(error.contains("didn't finish"));
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let command = ["echo".to_string(), "hello".to_string()];
+        let output = run_command(&command, Path::new("."), Duration::from_secs(5)).unwrap();
+        assert_eq!(output.stdout, "hello\n");
+    }
+}
@@ -1,46 +1,81 @@
+pub mod clippy;
+pub mod command;
+pub mod eslint;
+pub mod external;
+pub mod path_matcher;
 pub mod regex;
 pub mod rule;
+pub mod rule_set;
+pub mod sarif;
 pub mod todo;
 
+use clippy::ClippyRule;
+use command::CommandRule;
+use eslint::EslintRule;
+use path_matcher::PatternSyntax;
 use regex::RegexRule;
-use rule::Rule;
+use rule::{Checker, Rule, RuleConfigError, Severity};
+use sarif::SarifRule;
 use serde::{Deserialize, Serialize};
 use todo::TodoRule;
 
-use crate::ratchet_file::Problem;
-
+/// Every rule type has the same `include`, `exclude`, `severity`, and `pattern_syntax` fields,
+/// which the macro below reads directly. Include and exclude are globs unless `pattern_syntax`
+/// is set to `Regex`. They're repeated on each type instead of flattened into a shared struct
+/// because serde can't deny unknown fields through a flatten
 #[non_exhaustive]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")] // Use a "type" field in the serialized data to distinguish rule types
 pub enum RatchetRule {
     Regex(RegexRule),
     Todo(TodoRule),
+    Command(CommandRule),
+    Clippy(ClippyRule),
+    Sarif(SarifRule),
+    Eslint(EslintRule),
     // Add other rule types here, make sure to add to the macro call below
 }
 
-/// Macro to call the inner type of each enum value to power the Rule trait functionality
+/// Macro to call the inner type of each enum value to power the Rule trait and the shared field accessors
 macro_rules! impl_functions_for_rule_types {
     ($($variant:ident),*) => {
         impl Rule for RatchetRule {
-            fn check(&self, path: &str, content: &str) -> Vec<Problem> {
+            fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+                match self {
+                    $(Self::$variant(rule) => rule.compile(),)*
+                }
+            }
+        }
+
+        impl RatchetRule {
+            /// Patterns for files the rule should be limited to
+            pub fn include(&self) -> Option<&[String]> {
+                match self {
+                    $(Self::$variant(rule) => rule.include.as_deref(),)*
+                }
+            }
+
+            /// Patterns for files the rule should never look at
+            pub fn exclude(&self) -> Option<&[String]> {
                 match self {
-                    $(Self::$variant(rule) => rule.check(path, content),)*
+                    $(Self::$variant(rule) => rule.exclude.as_deref(),)*
                 }
             }
 
-            fn include(&self) -> Option<Vec<::regex::Regex>> {
+            pub fn severity(&self) -> Severity {
                 match self {
-                    $(Self::$variant(rule) => rule.include(),)*
+                    $(Self::$variant(rule) => rule.severity,)*
                 }
             }
 
-            fn exclude(&self) -> Option<Vec<::regex::Regex>> {
+            /// How the include and exclude patterns should be interpreted
+            pub fn pattern_syntax(&self) -> PatternSyntax {
                 match self {
-                    $(Self::$variant(rule) => rule.exclude(),)*
+                    $(Self::$variant(rule) => rule.pattern_syntax,)*
                 }
             }
         }
     };
 }
 
-impl_functions_for_rule_types!(Regex, Todo);
+impl_functions_for_rule_types!(Regex, Todo, Command, Clippy, Sarif, Eslint);
@@ -0,0 +1,140 @@
+use std::fmt;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+/// How a rule's include and exclude patterns should be interpreted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PatternSyntax {
+    /// Gitignore style globs, like `src/**/*.rs` or `*.generated.ts`
+    #[default]
+    Glob,
+    Regex,
+}
+
+/// Matches repo-relative paths, without a leading `./`, against a list of patterns
+pub enum PathMatcher {
+    Glob(GlobSet),
+    Regex(RegexSet),
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Glob(globset::Error),
+    Regex(regex::Error),
+    /// The setting is well formed but can't be used, the message says why
+    Invalid(String),
+}
+
+impl PathMatcher {
+    pub fn new(patterns: &[String], syntax: PatternSyntax) -> Result<Self, PatternError> {
+        match syntax {
+            PatternSyntax::Glob => {
+                let mut builder = GlobSetBuilder::new();
+                for pattern in patterns {
+                    for glob in to_globs(pattern) {
+                        let glob = GlobBuilder::new(&glob)
+                            .literal_separator(true)
+                            .build()
+                            .map_err(PatternError::Glob)?;
+                        builder.add(glob);
+                    }
+                }
+                Ok(PathMatcher::Glob(
+                    builder.build().map_err(PatternError::Glob)?,
+                ))
+            }
+            PatternSyntax::Regex => Ok(PathMatcher::Regex(
+                RegexSet::new(patterns).map_err(PatternError::Regex)?,
+            )),
+        }
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            PathMatcher::Glob(globs) => globs.is_match(path),
+            PathMatcher::Regex(regexes) => regexes.is_match(path),
+        }
+    }
+}
+
+/// Turns a gitignore style pattern into the globs that match the same paths.
+/// Patterns without a slash match at any depth, a leading slash anchors the pattern to the root,
+/// and anything matching a directory matches everything inside of it too.
+fn to_globs(pattern: &str) -> [String; 2] {
+    let trimmed = pattern.trim_end_matches('/');
+    let glob = match trimmed.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if !trimmed.contains('/') => format!("**/{trimmed}"),
+        None => trimmed.to_string(),
+    };
+
+    let children = format!("{glob}/**");
+    [glob, children]
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Glob(error) => write!(f, "{error}"),
+            PatternError::Regex(error) => write!(f, "{error}"),
+            PatternError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatternError::Glob(error) => Some(error),
+            PatternError::Regex(error) => Some(error),
+            PatternError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<regex::Error> for PatternError {
+    fn from(error: regex::Error) -> Self {
+        PatternError::Regex(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PathMatcher, PatternSyntax};
+
+    fn matcher(pattern: &str) -> PathMatcher {
+        PathMatcher::new(&[pattern.to_string()], PatternSyntax::Glob).unwrap()
+    }
+
+    #[test]
+    fn globs_follow_gitignore_rules() {
+        assert!(matcher("src/**/*").is_match("src/rules/todo.rs"));
+        assert!(!matcher("src/**/*").is_match("packages/src/lib.rs"));
+
+        // No slash means it can match at any depth
+        assert!(matcher("*.rs").is_match("packages/ratchet/src/lib.rs"));
+        assert!(matcher("*.rs").is_match("lib.rs"));
+
+        // A single star doesn't cross directories
+        assert!(!matcher("src/*.rs").is_match("src/rules/todo.rs"));
+
+        // Directories match everything inside them
+        assert!(matcher("node_modules/").is_match("web/node_modules/react/index.js"));
+        assert!(matcher("/dist").is_match("dist/bundle.js"));
+        assert!(!matcher("/dist").is_match("web/dist/bundle.js"));
+    }
+
+    #[test]
+    fn regex_is_opt_in() {
+        let regex = PathMatcher::new(&[r"^src/.*\.rs$".to_string()], PatternSyntax::Regex).unwrap();
+
+        assert!(regex.is_match("src/main.rs"));
+        assert!(!regex.is_match("lib/main.rs"));
+        assert!(matches!(
+            PathMatcher::new(&["[".to_string()], PatternSyntax::Glob),
+            Err(super::PatternError::Glob(_))
+        ));
+    }
+}
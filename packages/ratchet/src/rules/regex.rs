@@ -1,50 +1,64 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::ratchet_file::Problem;
+use crate::{
+    ratchet_file::Problem,
+    rules::{
+        path_matcher::PatternSyntax,
+        rule::{Checker, RuleConfigError, Severity},
+    },
+    utils::{LineIndex, fingerprint},
+};
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct RegexRule {
     pub regex: String,
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub pattern_syntax: PatternSyntax,
 }
 
 impl RegexRule {
-    pub fn check(&self, path: &str, content: &str) -> Vec<Problem> {
-        let mut problems: Vec<Problem> = Vec::new();
+    pub fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+        Ok(Box::new(RegexChecker::new(&self.regex)?))
+    }
+}
 
-        let rule_regex = Regex::new(&self.regex).expect("Failed to compile regex");
-        let matches: Vec<_> = rule_regex.find_iter(content).collect();
-        println!("Found {} matches for {}", matches.len(), path);
-        for found in matches {
-            let value = (
-                found.start(),
-                found.end(),
-                rule_regex.to_string(),
-                seahash::hash(rule_regex.as_str().as_bytes()).to_string(),
-            );
-            problems.push(value);
-        }
+/// Reports a problem for every match of a regex
+pub struct RegexChecker {
+    regex: Regex,
+    hash: String,
+}
 
-        problems
-    }
+impl RegexChecker {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = Regex::new(pattern)?;
+        let hash = seahash::hash(regex.as_str().as_bytes()).to_string();
 
-    pub fn include(&self) -> Option<Vec<Regex>> {
-        self.include.as_ref().map(|include| {
-            include
-                .iter()
-                .map(|i| Regex::new(i).expect("Failed to compile include regex"))
-                .collect()
-        })
+        Ok(RegexChecker { regex, hash })
     }
+}
+
+impl Checker for RegexChecker {
+    fn check(&self, _path: &str, content: &str) -> Result<Vec<Problem>, String> {
+        let mut problems: Vec<Problem> = Vec::new();
+
+        let line_index = LineIndex::new(content);
+        for found in self.regex.find_iter(content) {
+            let value = Problem {
+                start: line_index.position(found.start()),
+                end: line_index.position(found.end()),
+                message: self.regex.to_string(),
+                message_hash: self.hash.clone(),
+                fingerprint: fingerprint(content, found.start(), found.end()),
+            };
+            problems.push(value);
+        }
 
-    pub fn exclude(&self) -> Option<Vec<Regex>> {
-        self.exclude.as_ref().map(|exclude| {
-            exclude
-                .iter()
-                .map(|e| Regex::new(e).expect("Failed to compile include regex"))
-                .collect()
-        })
+        Ok(problems)
     }
 }
@@ -1,36 +1,81 @@
-use regex::Regex;
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
 
 use crate::ratchet_file::Problem;
 
-pub trait Rule {
-    /// Default implementation to determine if a file should be analyzed
-    fn analyze_file(&self, path: &str) -> bool {
-        if self.include().is_some() {
-            let include = self.include().unwrap();
-            if !include.iter().any(|r| r.is_match(path)) {
-                return false;
-            }
+/// How much a rule getting worse matters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Getting worse fails `ratchet check`
+    #[default]
+    Error,
+    /// Getting worse is reported, but doesn't fail anything
+    Warn,
+    /// The rule isn't run and its previous results are kept as they are
+    Off,
+}
+
+/// Why a rule's own settings couldn't be compiled into a checker
+#[derive(Debug)]
+pub enum RuleConfigError {
+    /// The rule's pattern isn't a valid regex
+    Pattern(regex::Error),
+    /// A setting can't be used as it is, like a pattern missing a group the rule needs
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl RuleConfigError {
+    pub fn invalid(field: &'static str, message: impl Into<String>) -> Self {
+        RuleConfigError::Invalid {
+            field,
+            message: message.into(),
         }
+    }
+}
 
-        if self.exclude().is_some() {
-            let exclude = self.exclude().unwrap();
-            if exclude.iter().any(|r| r.is_match(path)) {
-                return false;
-            }
+impl fmt::Display for RuleConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleConfigError::Pattern(error) => write!(f, "{error}"),
+            RuleConfigError::Invalid { message, .. } => write!(f, "{message}"),
         }
+    }
+}
 
-        true
+impl std::error::Error for RuleConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RuleConfigError::Pattern(error) => Some(error),
+            RuleConfigError::Invalid { .. } => None,
+        }
     }
+}
 
-    fn include(&self) -> Option<Vec<Regex>> {
-        None
+impl From<regex::Error> for RuleConfigError {
+    fn from(error: regex::Error) -> Self {
+        RuleConfigError::Pattern(error)
     }
+}
+
+pub trait Rule {
+    /// Compile is called once per run to turn the rule's configuration into something
+    /// that can check files, so nothing has to be rebuilt for every file
+    fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError>;
+}
 
-    fn exclude(&self) -> Option<Vec<Regex>> {
-        None
+/// A compiled rule, ready to be shared across threads and run against many files
+pub trait Checker: Send + Sync {
+    /// Called once per run before any file is checked, for rules that look at the whole project at once
+    fn prepare(&self, _root: &Path) -> Result<(), String> {
+        Ok(())
     }
 
     /// Check is the main function that will be called to determine if a file has any problems
     /// and every type of rule will need it's own implementation
-    fn check(&self, path: &str, content: &str) -> Vec<Problem>;
+    fn check(&self, path: &str, content: &str) -> Result<Vec<Problem>, String>;
 }
@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use crate::{
+    error::{self, RatchetError},
+    ratchet_file::{Problem, RuleName},
+    rules::{
+        RatchetRule,
+        path_matcher::{PathMatcher, PatternError},
+        rule::{Checker, Rule, RuleConfigError, Severity},
+    },
+};
+
+/// Every rule from the config, compiled once up front so a run never rebuilds a regex per file
+pub struct RuleSet {
+    pub rules: Vec<CompiledRule>,
+}
+
+pub struct CompiledRule {
+    pub name: RuleName,
+    pub severity: Severity,
+    include: Option<PathMatcher>,
+    exclude: Option<PathMatcher>,
+    checker: Box<dyn Checker>,
+}
+
+/// Which part of a rule's configuration failed to compile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternKind {
+    Rule,
+    Include,
+    Exclude,
+    /// Any other setting, by its name in the config
+    Setting(&'static str),
+}
+
+#[derive(Debug)]
+pub struct RuleCompileError {
+    pub rule: RuleName,
+    pub kind: PatternKind,
+    pub source: PatternError,
+}
+
+impl fmt::Display for RuleCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            PatternKind::Rule => "pattern".to_string(),
+            PatternKind::Include => "include pattern".to_string(),
+            PatternKind::Exclude => "exclude pattern".to_string(),
+            PatternKind::Setting(field) => format!("`{field}`"),
+        };
+        write!(
+            f,
+            "Rule \"{}\" has an invalid {}: {}",
+            self.rule, kind, self.source
+        )
+    }
+}
+
+impl std::error::Error for RuleCompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl RuleSet {
+    /// Compiles every rule, returning all the rules that failed instead of stopping at the first
+    pub fn compile(rules: &BTreeMap<RuleName, RatchetRule>) -> Result<Self, Vec<RuleCompileError>> {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+
+        for (name, rule) in rules {
+            match CompiledRule::compile(name, rule) {
+                Ok(rule) => compiled.push(rule),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(RuleSet { rules: compiled })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl CompiledRule {
+    pub fn compile(name: &str, rule: &RatchetRule) -> Result<Self, RuleCompileError> {
+        let error = |kind, source| RuleCompileError {
+            rule: name.to_string(),
+            kind,
+            source,
+        };
+
+        let syntax = rule.pattern_syntax();
+        let include = rule
+            .include()
+            .map(|patterns| PathMatcher::new(patterns, syntax))
+            .transpose()
+            .map_err(|e| error(PatternKind::Include, e))?;
+        let exclude = rule
+            .exclude()
+            .map(|patterns| PathMatcher::new(patterns, syntax))
+            .transpose()
+            .map_err(|e| error(PatternKind::Exclude, e))?;
+        let checker = rule.compile().map_err(|e| match e {
+            RuleConfigError::Pattern(e) => error(PatternKind::Rule, e.into()),
+            RuleConfigError::Invalid { field, message } => {
+                error(PatternKind::Setting(field), PatternError::Invalid(message))
+            }
+        })?;
+
+        Ok(CompiledRule {
+            name: name.to_string(),
+            severity: rule.severity(),
+            include,
+            exclude,
+            checker,
+        })
+    }
+
+    /// Determines if a file should be analyzed based on the include and exclude patterns.
+    /// Paths are relative to the root of the scan, without a leading `./`
+    pub fn analyze_file(&self, path: &str) -> bool {
+        if let Some(include) = &self.include
+            && !include.is_match(path)
+        {
+            return false;
+        }
+
+        if let Some(exclude) = &self.exclude
+            && exclude.is_match(path)
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// Lets the rule look at the whole project before any file is checked
+    pub fn prepare(&self, root: &Path) -> error::Result<()> {
+        self.checker
+            .prepare(root)
+            .map_err(|message| self.failed(message))
+    }
+
+    pub fn check(&self, path: &str, content: &str) -> error::Result<Vec<Problem>> {
+        self.checker
+            .check(path, content)
+            .map_err(|message| self.failed(message))
+    }
+
+    fn failed(&self, message: String) -> RatchetError {
+        RatchetError::RuleFailed {
+            rule: self.name.clone(),
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{PatternKind, RuleSet};
+    use crate::rules::{
+        RatchetRule, path_matcher::PatternSyntax, regex::RegexRule, rule::Severity,
+    };
+
+    fn regex_rule(regex: &str, include: Option<&str>, exclude: Option<&str>) -> RatchetRule {
+        RatchetRule::Regex(RegexRule {
+            regex: regex.into(),
+            include: include.map(|i| vec![i.into()]),
+            exclude: exclude.map(|e| vec![e.into()]),
+            severity: Severity::Error,
+            pattern_syntax: PatternSyntax::Glob,
+        })
+    }
+
+    #[test]
+    fn include_and_exclude_limit_analyzed_files() {
+        let mut rules = BTreeMap::new();
+        rules.insert(
+            "rule".to_string(),
+            regex_rule("needle", Some("*.rs"), Some("main.rs")),
+        );
+
+        let rule_set = RuleSet::compile(&rules).unwrap();
+        let rule = &rule_set.rules[0];
+
+        assert!(rule.analyze_file("src/lib.rs"));
+        assert!(!rule.analyze_file("src/main.rs"));
+        assert!(!rule.analyze_file("README.md"));
+    }
+
+    #[test]
+    fn invalid_patterns_report_every_rule() {
+        let mut rules = BTreeMap::new();
+        rules.insert("bad regex".to_string(), regex_rule("(", None, None));
+        rules.insert("bad include".to_string(), regex_rule("ok", Some("["), None));
+        rules.insert("good".to_string(), regex_rule("ok", None, None));
+
+        let errors = RuleSet::compile(&rules).err().unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].rule, "bad include");
+        assert_eq!(errors[0].kind, PatternKind::Include);
+        assert_eq!(errors[1].rule, "bad regex");
+        assert_eq!(errors[1].kind, PatternKind::Rule);
+    }
+}
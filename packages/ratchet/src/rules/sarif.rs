@@ -0,0 +1,399 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ratchet_file::{Position, Problem},
+    rules::{
+        external::{
+            ExternalIssue, IssueMap, ProjectIssues, absolute_root, is_wanted, relative_path,
+        },
+        path_matcher::PatternSyntax,
+        rule::{Checker, RuleConfigError, Severity},
+    },
+};
+
+/// The level SARIF uses when neither the result nor its rule set one
+const DEFAULT_LEVEL: &str = "warning";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SarifRule {
+    /// SARIF logs to read, relative to the working directory
+    pub files: Vec<PathBuf>,
+    /// Only keep results for these rules, like `python.eval-used`
+    pub rule_ids: Option<Vec<String>>,
+    /// Only keep results at these levels: `error`, `warning`, `note` or `none`
+    pub levels: Option<Vec<String>>,
+    /// Directory the logs are read from and relative paths in them are resolved against,
+    /// relative to the root. Defaults to the root
+    pub working_dir: Option<PathBuf>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub pattern_syntax: PatternSyntax,
+}
+
+impl SarifRule {
+    pub fn compile(&self) -> Result<Box<dyn Checker>, RuleConfigError> {
+        Ok(Box::new(SarifChecker {
+            files: self.files.clone(),
+            rule_ids: self.rule_ids.clone(),
+            levels: self.levels.clone(),
+            working_dir: self.working_dir.clone().unwrap_or_default(),
+            issues: ProjectIssues::default(),
+        }))
+    }
+}
+
+/// Just the parts of a SARIF log needed to find where each result is
+#[derive(Deserialize)]
+struct SarifLog {
+    #[serde(default)]
+    runs: Vec<Run>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    #[serde(default)]
+    original_uri_base_ids: HashMap<String, ArtifactLocation>,
+    #[serde(default)]
+    results: Vec<SarifResult>,
+}
+
+#[derive(Deserialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Deserialize)]
+struct Driver {
+    #[serde(default)]
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    default_configuration: Option<Configuration>,
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    level: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: Option<String>,
+    rule_index: Option<usize>,
+    level: Option<String>,
+    message: Message,
+    #[serde(default)]
+    locations: Vec<Location>,
+    #[serde(default)]
+    suppressions: Vec<serde_json::Value>,
+    baseline_state: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: Option<PhysicalLocation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: Option<ArtifactLocation>,
+    region: Option<Region>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: Option<String>,
+    uri_base_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: Option<usize>,
+    start_column: Option<usize>,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+}
+
+/// Turns the results in SARIF logs into problems in the files they point at
+pub struct SarifChecker {
+    files: Vec<PathBuf>,
+    rule_ids: Option<Vec<String>>,
+    levels: Option<Vec<String>>,
+    working_dir: PathBuf,
+    issues: ProjectIssues,
+}
+
+impl SarifChecker {
+    fn keep(&self, rule_id: Option<&str>, level: &str) -> bool {
+        is_wanted(self.rule_ids.as_deref(), rule_id)
+            && is_wanted(self.levels.as_deref(), Some(level))
+    }
+
+    fn add_run(&self, run: &Run, root: &Path, working_dir: &Path, issues: &mut IssueMap) {
+        for result in &run.results {
+            // Suppressed results were accepted in the tool itself, and absent ones are already gone
+            if !result.suppressions.is_empty() || result.baseline_state.as_deref() == Some("absent")
+            {
+                continue;
+            }
+
+            let rule = result
+                .rule_index
+                .and_then(|index| run.tool.driver.rules.get(index))
+                .or_else(|| {
+                    let rule_id = result.rule_id.as_ref()?;
+                    run.tool
+                        .driver
+                        .rules
+                        .iter()
+                        .find(|rule| &rule.id == rule_id)
+                });
+            let rule_id = result
+                .rule_id
+                .as_deref()
+                .or(rule.map(|rule| rule.id.as_str()));
+            let level = result
+                .level
+                .as_deref()
+                .or_else(|| rule?.default_configuration.as_ref()?.level.as_deref())
+                .unwrap_or(DEFAULT_LEVEL);
+            if !self.keep(rule_id, level) {
+                continue;
+            }
+
+            // Only the first location is where the problem is, the rest are related places
+            let Some(location) = result
+                .locations
+                .first()
+                .and_then(|location| location.physical_location.as_ref())
+            else {
+                continue;
+            };
+            let Some(path) = location
+                .artifact_location
+                .as_ref()
+                .and_then(|artifact| artifact_path(run, artifact))
+                .and_then(|path| relative_path(root, working_dir, &path))
+            else {
+                continue;
+            };
+
+            let region = location.region.as_ref();
+            let start_line = region.and_then(|region| region.start_line).unwrap_or(1);
+            let start = Position::new(
+                start_line,
+                region.and_then(|region| region.start_column).unwrap_or(1),
+            );
+            let end = region.and_then(|region| {
+                let column = region.end_column?;
+                Some(Position::new(region.end_line.unwrap_or(start_line), column))
+            });
+
+            let message = result
+                .message
+                .text
+                .clone()
+                .or(rule_id.map(String::from))
+                .unwrap_or_default();
+
+            issues.entry(path).or_default().push(ExternalIssue {
+                start,
+                end,
+                message,
+                code: rule_id.map(String::from),
+            });
+        }
+    }
+}
+
+impl Checker for SarifChecker {
+    fn prepare(&self, root: &Path) -> Result<(), String> {
+        let root = absolute_root(root)?;
+        let working_dir = root.join(&self.working_dir);
+
+        let mut issues = IssueMap::new();
+        for file in &self.files {
+            let path = working_dir.join(file);
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            let log: SarifLog = serde_json::from_str(&contents)
+                .map_err(|e| format!("{} isn't a valid SARIF log: {e}", path.display()))?;
+
+            for run in &log.runs {
+                self.add_run(run, &root, &working_dir, &mut issues);
+            }
+        }
+        self.issues.set(issues);
+
+        Ok(())
+    }
+
+    fn check(&self, path: &str, content: &str) -> Result<Vec<Problem>, String> {
+        Ok(self.issues.check(path, content))
+    }
+}
+
+/// Resolves an artifact's URI, following its base id if it has one.
+/// Relative URIs without a base are left for the caller to resolve against the working directory
+fn artifact_path(run: &Run, artifact: &ArtifactLocation) -> Option<String> {
+    let uri = artifact.uri.as_deref()?;
+    if let Some(path) = file_uri_path(uri) {
+        return Some(path);
+    }
+    if uri.contains("://") {
+        return None;
+    }
+
+    let path = percent_decode(uri);
+    let base = artifact
+        .uri_base_id
+        .as_ref()
+        .and_then(|base_id| run.original_uri_base_ids.get(base_id))
+        .and_then(|base| artifact_path(run, base));
+
+    match base {
+        Some(base) => Some(Path::new(&base).join(path).to_string_lossy().into_owned()),
+        None => Some(path),
+    }
+}
+
+/// The path in a `file://` URI, if it is one
+fn file_uri_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://")?;
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let path = percent_decode(path);
+
+    // Windows paths look like `/C:/src/main.rs`
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return Some(path[1..].to_string());
+    }
+    Some(path)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::SarifRule;
+    use crate::{
+        ratchet_file::{Position, Problem},
+        rules::external::test::{check_fixture, fixture_root, from_settings},
+    };
+
+    /// A rule reading both recorded logs, with any other settings added on
+    fn rule(settings: &str) -> SarifRule {
+        from_settings(&format!(
+            "files = [\"results.sarif\", \"more.sarif\"]\n{settings}"
+        ))
+    }
+
+    fn check(rule: SarifRule) -> Vec<Problem> {
+        check_fixture(
+            rule.compile().unwrap(),
+            &fixture_root("sarif"),
+            "src/app.py",
+        )
+    }
+
+    #[test]
+    fn reads_results_from_every_log() {
+        let problems = check(rule(""));
+
+        let found: Vec<(Position, Position, &str)> = problems
+            .iter()
+            .map(|problem| (problem.start, problem.end, problem.message_hash.as_str()))
+            .collect();
+        // Suppressed results and ones outside of the project are left out
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Position::new(5, 12),
+                    Position::new(5, 48),
+                    "python.subprocess-shell-true"
+                ),
+                (Position::new(8, 1), Position::new(8, 4), "python.style"),
+                // Without an end column the problem runs to the end of the line
+                (
+                    Position::new(9, 12),
+                    Position::new(9, 22),
+                    "python.eval-used"
+                ),
+            ]
+        );
+        assert_eq!(problems[0].message, "Found subprocess call with shell=True");
+    }
+
+    #[test]
+    fn filters_by_rule_id_and_level() {
+        let problems = check(rule("rule_ids = [\"python.eval-used\"]"));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message, "Detected use of eval");
+
+        // The first result gets its level from the rule's default configuration
+        let errors = check(rule("levels = [\"error\"]"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message_hash, "python.subprocess-shell-true");
+    }
+
+    #[test]
+    fn resolves_file_uris() {
+        assert_eq!(
+            super::file_uri_path("file:///home/me/my%20project/a.py"),
+            Some("/home/me/my project/a.py".to_string())
+        );
+        assert_eq!(
+            super::file_uri_path("file:///C:/src/a.py"),
+            Some("C:/src/a.py".to_string())
+        );
+        assert_eq!(super::file_uri_path("src/a.py"), None);
+    }
+}
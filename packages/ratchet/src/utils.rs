@@ -1,11 +1,15 @@
-use std::path::Path;
+use std::{hash::Hasher, path::Path};
 
 use regex::Regex;
+use seahash::SeaHasher;
+
+use crate::ratchet_file::Position;
 
 /// Normalizes the path between operating systems
 /// by replacing any backslashes with forward slashes.
+/// Names that aren't valid UTF-8 get replacement characters instead of failing the scan.
 pub fn to_normalized_path(path: &Path) -> String {
-    path.to_str().unwrap().replace("\\", "/")
+    path.to_string_lossy().replace("\\", "/")
 }
 
 /// Normalizes the file contents between operating systems
@@ -15,6 +19,73 @@ pub fn to_normalized_file_contents(contents: &str) -> String {
     re.replace_all(contents, "\n").to_string()
 }
 
+/// Builds a fingerprint for a problem from the matched text and the lines it sits on.
+/// Leading and trailing whitespace is ignored, so edits elsewhere in the file,
+/// re-indenting, or renaming the file won't change it.
+pub fn fingerprint(content: &str, start: usize, end: usize) -> String {
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[end..].find('\n').map_or(content.len(), |i| end + i);
+
+    let lines = content[line_start..line_end]
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut hasher = SeaHasher::new();
+    hasher.write(&content.as_bytes()[start..end]);
+    hasher.write(lines.as_bytes());
+    hasher.finish().to_string()
+}
+
+/// Converts byte offsets in a file into line and column positions
+pub struct LineIndex<'a> {
+    content: &'a str,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(content: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex {
+            content,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.content[self.line_starts[line]..offset].chars().count();
+
+        Position::new(line + 1, column + 1)
+    }
+
+    /// Byte offset of a position, the inverse of `position`.
+    /// Positions past the end of a line or the file are clamped to it, tools can report stale positions
+    pub fn offset(&self, position: Position) -> usize {
+        let line = position.line.clamp(1, self.line_starts.len()) - 1;
+        let start = self.line_starts[line];
+        let end = self.line_end(position.line);
+
+        self.content[start..end]
+            .char_indices()
+            .nth(position.column.saturating_sub(1))
+            .map_or(end, |(index, _)| start + index)
+    }
+
+    /// Byte offset of the end of a 1-based line, not including the newline
+    pub fn line_end(&self, line: usize) -> usize {
+        let line = line.clamp(1, self.line_starts.len()) - 1;
+        self.line_starts
+            .get(line + 1)
+            .map_or(self.content.len(), |next| next - 1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -25,9 +96,69 @@ mod tests {
         assert_eq!(to_normalized_path(path), "foo/bar");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_to_normalized_path_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = Path::new(OsStr::from_bytes(b"foo/b\xffr.rs"));
+        assert_eq!(to_normalized_path(path), "foo/b\u{FFFD}r.rs");
+    }
+
     #[test]
     fn test_to_normalized_file_contents() {
         let contents = "foo\r\nbar\r";
         assert_eq!(to_normalized_file_contents(contents), "foo\nbar\n");
     }
+
+    #[test]
+    fn test_fingerprint_ignores_other_lines_and_indentation() {
+        let before = "fn main() {\n    // TODO: fix\n}\n";
+        let after = "use std::io;\n\nfn main() {\n        // TODO: fix\n}\n";
+
+        let before_start = before.find("TODO").unwrap();
+        let after_start = after.find("TODO").unwrap();
+
+        assert_eq!(
+            fingerprint(before, before_start, before_start + 4),
+            fingerprint(after, after_start, after_start + 4)
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_line() {
+        let before = "// TODO: fix\n";
+        let after = "// TODO: fix this now\n";
+
+        assert_ne!(fingerprint(before, 3, 7), fingerprint(after, 3, 7));
+    }
+
+    #[test]
+    fn test_line_index_position() {
+        let content = "foo\nbär baz\n";
+        let index = LineIndex::new(content);
+
+        assert_eq!(index.position(0), Position::new(1, 1));
+        assert_eq!(index.position(4), Position::new(2, 1));
+        // The umlaut is two bytes but only one column
+        assert_eq!(
+            index.position(content.find("baz").unwrap()),
+            Position::new(2, 5)
+        );
+        assert_eq!(index.position(content.len()), Position::new(3, 1));
+    }
+
+    #[test]
+    fn test_line_index_offset() {
+        let content = "foo\nbär baz\n";
+        let index = LineIndex::new(content);
+
+        let baz = content.find("baz").unwrap();
+        assert_eq!(index.offset(index.position(baz)), baz);
+        assert_eq!(index.offset(Position::new(1, 1)), 0);
+        // Columns and lines past the end are clamped
+        assert_eq!(index.offset(Position::new(1, 50)), 3);
+        assert_eq!(index.offset(Position::new(50, 1)), content.len());
+        assert_eq!(index.line_end(2), content.len() - 1);
+    }
 }
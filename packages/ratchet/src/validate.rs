@@ -0,0 +1,211 @@
+use std::{collections::BTreeMap, fmt, fs, ops::Range, path::PathBuf};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    config::{CONFIG_VERSION, RatchetConfig},
+    error::{RatchetError, Result},
+    ratchet_file::Position,
+    rules::rule_set::{PatternKind, RuleSet},
+    utils::LineIndex,
+};
+
+/// A single problem with the config file, pointing at where it is when possible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    pub path: PathBuf,
+    pub position: Option<Position>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{}:{}: {}", self.path.display(), position, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Where each field of a rule is, keyed by the field's name
+type RuleSpans = BTreeMap<Spanned<String>, Spanned<toml::Value>>;
+
+/// Mirrors the shape of the config, but only to find out where things are in the file
+#[derive(Deserialize)]
+struct ConfigSpans {
+    version: Spanned<toml::Value>,
+    #[serde(default)]
+    rules: BTreeMap<String, Spanned<RuleSpans>>,
+}
+
+impl ConfigSpans {
+    /// Serde points an unknown field at the rule it's in, this finds the field itself instead
+    fn unknown_field(contents: &str, error: &toml::de::Error) -> Option<Range<usize>> {
+        let field = error
+            .message()
+            .strip_prefix("unknown field `")?
+            .split('`')
+            .next()?;
+        let error_span = error.span()?;
+        let spans: ConfigSpans = toml::from_str(contents).ok()?;
+
+        spans
+            .rules
+            .values()
+            .filter(|rule| rule.span().contains(&error_span.start))
+            .find_map(|rule| rule.get_ref().get_key_value(field))
+            .map(|(key, _)| key.span())
+    }
+}
+
+/// Reads the config and makes sure everything in it is usable, compiling the rules along the way
+pub fn load_config(config_path: &str) -> Result<(RatchetConfig, RuleSet)> {
+    let contents = fs::read_to_string(config_path).map_err(|e| RatchetError::io(config_path, e))?;
+
+    let (config, rule_set) = validate_config(config_path, &contents).map_err(|diagnostics| {
+        RatchetError::InvalidConfig {
+            path: config_path.into(),
+            diagnostics,
+        }
+    })?;
+
+    Ok((config, rule_set))
+}
+
+fn validate_config(
+    config_path: &str,
+    contents: &str,
+) -> std::result::Result<(RatchetConfig, RuleSet), Vec<ConfigDiagnostic>> {
+    let line_index = LineIndex::new(contents);
+    let diagnostic = |span: Option<Range<usize>>, message: String| ConfigDiagnostic {
+        path: config_path.into(),
+        position: span.map(|span| line_index.position(span.start)),
+        message,
+    };
+
+    // Shape problems like bad TOML, unknown fields, or unknown rule types come straight from serde
+    let config: RatchetConfig = toml::from_str(contents).map_err(|error| {
+        let span = ConfigSpans::unknown_field(contents, &error).or(error.span());
+        vec![diagnostic(span, error.message().trim().to_string())]
+    })?;
+    let spans: ConfigSpans = toml::from_str(contents)
+        .map_err(|error| vec![diagnostic(error.span(), error.message().trim().to_string())])?;
+
+    let mut diagnostics = Vec::new();
+
+    if config.version != CONFIG_VERSION {
+        diagnostics.push(diagnostic(
+            Some(spans.version.span()),
+            format!(
+                "Config version {} is not supported, expected version {}",
+                config.version, CONFIG_VERSION
+            ),
+        ));
+    }
+
+    let rule_set = match RuleSet::compile(&config.rules) {
+        Ok(rule_set) => Some(rule_set),
+        Err(errors) => {
+            for error in errors {
+                let fields: &[&str] = match &error.kind {
+                    PatternKind::Rule => &["regex", "pattern"],
+                    PatternKind::Include => &["include"],
+                    PatternKind::Exclude => &["exclude"],
+                    PatternKind::Setting(field) => std::slice::from_ref(field),
+                };
+                let span = spans.rules.get(&error.rule).map(|rule| {
+                    fields
+                        .iter()
+                        .find_map(|field| rule.get_ref().get(*field))
+                        .map_or(rule.span(), |value| value.span())
+                });
+                diagnostics.push(diagnostic(span, error.to_string()));
+            }
+            None
+        }
+    };
+
+    match rule_set {
+        Some(rule_set) if diagnostics.is_empty() => Ok((config, rule_set)),
+        _ => Err(diagnostics),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::validate_config;
+
+    fn diagnostics(contents: &str) -> Vec<String> {
+        validate_config("ratchet.toml", contents)
+            .err()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn valid_config_compiles_rules() {
+        let (config, rule_set) = validate_config(
+            "ratchet.toml",
+            "version = 1\n\n[rules.todos]\ntype = \"Todo\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(rule_set.rules[0].name, "todos");
+    }
+
+    #[test]
+    fn unknown_rule_types_and_fields_point_at_the_line() {
+        let unknown_type = diagnostics("version = 1\n\n[rules.todos]\ntype = \"Nope\"\n");
+        assert_eq!(unknown_type.len(), 1);
+        assert!(unknown_type[0].starts_with("ratchet.toml:4:8: unknown variant `Nope`"));
+
+        let unknown_field =
+            diagnostics("version = 1\n\n[rules.todos]\ntype = \"Todo\"\nincldue = [\"src\"]\n");
+        assert_eq!(unknown_field.len(), 1);
+        assert!(unknown_field[0].starts_with("ratchet.toml:5:1: unknown field `incldue`"));
+    }
+
+    #[test]
+    fn version_and_pattern_problems_are_all_reported() {
+        let contents = "version = 9\n\n[rules.hacks]\ntype = \"Regex\"\nregex = \"(\"\n\n[rules.todos]\ntype = \"Todo\"\nexclude = [\"[\"]\n";
+
+        let diagnostics = diagnostics(contents);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0].starts_with("ratchet.toml:1:11: Config version 9 is not supported"));
+        assert!(
+            diagnostics[1].starts_with("ratchet.toml:5:9: Rule \"hacks\" has an invalid pattern")
+        );
+        assert!(
+            diagnostics[2]
+                .starts_with("ratchet.toml:9:11: Rule \"todos\" has an invalid exclude pattern")
+        );
+    }
+
+    #[test]
+    fn invalid_settings_point_at_the_setting() {
+        let contents = "version = 1\n\n[rules.mypy]\ntype = \"Command\"\ncommand = [\"mypy\"]\npattern = '^(?P<file>.*)$'\n";
+
+        let diagnostics = diagnostics(contents);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0],
+            "ratchet.toml:6:11: Rule \"mypy\" has an invalid `pattern`: pattern needs a `line` named group"
+        );
+    }
+
+    #[test]
+    fn conflicting_sources_are_caught_before_running() {
+        let contents = "version = 1\n\n[rules.clippy]\ntype = \"Clippy\"\nfile = \"clippy.json\"\ncommand = [\"cargo\", \"clippy\"]\n";
+
+        let diagnostics = diagnostics(contents);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .starts_with("ratchet.toml:6:11: Rule \"clippy\" has an invalid `command`")
+        );
+    }
+}
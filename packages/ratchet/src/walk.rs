@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use ignore::{DirEntry, WalkBuilder};
+
+use crate::{
+    config::{RATCHET_IGNORE, WELL_KNOWN_DIRS, WELL_KNOWN_FILES, WELL_KNOWN_ROOT_DIRS},
+    error::Result,
+};
+
+/// Walks the tree from the given root and returns every file that isn't ignored or well known.
+/// The `managed` files, like the config and ratchet file in use, are skipped too whatever they're named
+pub fn collect_files(
+    root: &Path,
+    use_ignore_files: bool,
+    managed: &[&Path],
+) -> Result<Vec<PathBuf>> {
+    let root_path = root.to_path_buf();
+    let managed: Vec<PathBuf> = managed
+        .iter()
+        .filter_map(|path| relative_to(root, path))
+        .collect();
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        // Hidden files can have problems too, only skip what's ignored or well known
+        .hidden(false)
+        .git_ignore(use_ignore_files)
+        .git_global(use_ignore_files)
+        .git_exclude(use_ignore_files)
+        .ignore(use_ignore_files)
+        .parents(use_ignore_files)
+        // Respect .gitignore files even if the project isn't in a git repository
+        .require_git(false)
+        .filter_entry(move |entry| {
+            !is_well_known(&root_path, entry)
+                && !entry
+                    .path()
+                    .strip_prefix(&root_path)
+                    .is_ok_and(|path| managed.iter().any(|managed| managed == path))
+        });
+
+    if use_ignore_files {
+        builder.add_custom_ignore_filename(RATCHET_IGNORE);
+    }
+
+    let mut paths = Vec::new();
+    for entry in builder.build() {
+        let entry = entry?;
+        // If it's not a file, there's nothing to analyze. Keep going!
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+
+        paths.push(entry.into_path());
+    }
+
+    Ok(paths)
+}
+
+/// Where a file is under the root, if it is. The file itself doesn't need to exist yet
+fn relative_to(root: &Path, path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let path = parent.canonicalize().ok()?.join(path.file_name()?);
+
+    path.strip_prefix(root.canonicalize().ok()?)
+        .ok()
+        .map(Path::to_path_buf)
+}
+
+fn is_well_known(root: &Path, entry: &DirEntry) -> bool {
+    let Some(file_name) = entry.file_name().to_str() else {
+        return false;
+    };
+
+    if entry
+        .file_type()
+        .is_some_and(|file_type| file_type.is_dir())
+    {
+        WELL_KNOWN_DIRS.contains(&file_name)
+            || (entry.path().parent() == Some(root) && WELL_KNOWN_ROOT_DIRS.contains(&file_name))
+    } else {
+        WELL_KNOWN_FILES.contains(&file_name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use super::collect_files;
+    use crate::utils::to_normalized_path;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn collect(root: &Path, use_ignore_files: bool) -> Vec<String> {
+        let managed = [root.join("lint.toml"), root.join("state/lint.ron")];
+        let managed: Vec<&Path> = managed.iter().map(PathBuf::as_path).collect();
+
+        let mut paths: Vec<String> = collect_files(root, use_ignore_files, &managed)
+            .unwrap()
+            .iter()
+            .map(|path| to_normalized_path(path.strip_prefix(root).unwrap()))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn ignore_files_and_well_known_paths_are_skipped() {
+        let root = std::env::temp_dir().join(format!("ratchet-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        write(&root, ".gitignore", "dist/\n");
+        write(&root, ".ratchetignore", "vendor/\n");
+        write(&root, "src/main.rs", "");
+        write(&root, "dist/bundle.js", "");
+        write(&root, "vendor/lib.js", "");
+        write(&root, "target/debug/out", "");
+        write(&root, "nested/target/kept.rs", "");
+        write(&root, "nested/ratchet.toml", "");
+        // Configs and ratchet files with other names are only skipped where they're in use
+        write(&root, "lint.toml", "");
+        write(&root, "state/lint.ron", "");
+        write(&root, "nested/lint.toml", "");
+        write(&root, ".git/HEAD", "");
+        write(&root, "legit.github/kept.yml", "");
+
+        let with_ignore_files = collect(&root, true);
+        let without_ignore_files = collect(&root, false);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            with_ignore_files,
+            vec![
+                ".gitignore",
+                ".ratchetignore",
+                "legit.github/kept.yml",
+                "nested/lint.toml",
+                "nested/target/kept.rs",
+                "src/main.rs",
+            ]
+        );
+        assert_eq!(
+            without_ignore_files,
+            vec![
+                ".gitignore",
+                ".ratchetignore",
+                "dist/bundle.js",
+                "legit.github/kept.yml",
+                "nested/lint.toml",
+                "nested/target/kept.rs",
+                "src/main.rs",
+                "vendor/lib.js",
+            ]
+        );
+    }
+}